@@ -0,0 +1,311 @@
+//! Support for export as Allure results.
+//!
+//! Unlike other formats, Allure reads a directory: every test is a `<uuid>-result.json`, suites and
+//! workflows are `<uuid>-container.json` grouping them, and captured output of failed tests goes to
+//! `<uuid>-attachment.txt`. Results of all workflows end up in the same `allure-results` directory.
+
+use crate::errors::SuityError;
+use crate::ids::guid;
+use crate::junit::{TestCase, TestSuite};
+use crate::panic::summarize_failure;
+use crate::report::{create_file, Reporter};
+use crate::runspec::Runspec;
+use std::io::{self, Write};
+use std::path::Path;
+use std::process;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Directory inside `output` results are written to.
+pub const RESULTS_DIR: &str = "allure-results";
+
+/// Writes Allure results.
+pub struct AllureReporter;
+
+impl Reporter for AllureReporter {
+    fn report(&self, runspec: &Runspec, suites: &[TestSuite]) -> Result<(), SuityError> {
+        Results::new(runspec, suites, &run_id()).write_to(&runspec.output.join(RESULTS_DIR))?;
+        Ok(())
+    }
+}
+
+/// Allure keeps results of previous runs next to new ones, ids must differ between runs.
+fn run_id() -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    format!("{}-{}", process::id(), now)
+}
+
+#[derive(Serialize)]
+struct Label<'a> {
+    name: &'static str,
+    value: &'a str,
+}
+
+#[derive(Serialize)]
+struct Parameter {
+    name: &'static str,
+    value: String,
+}
+
+#[derive(Serialize)]
+struct StatusDetails<'a> {
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    trace: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+struct AttachmentRef {
+    name: &'static str,
+    source: String,
+    #[serde(rename = "type")]
+    content_type: &'static str,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TestResult<'a> {
+    uuid: String,
+    /// Same for the same test on every run, Allure tracks history of a test by it.
+    history_id: String,
+    name: &'a str,
+    full_name: String,
+    status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status_details: Option<StatusDetails<'a>>,
+    stage: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    start: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop: Option<u64>,
+    labels: Vec<Label<'a>>,
+    parameters: Vec<Parameter>,
+    attachments: Vec<AttachmentRef>,
+}
+
+#[derive(Serialize)]
+struct Container<'a> {
+    uuid: String,
+    name: &'a str,
+    children: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    start: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop: Option<u64>,
+}
+
+/// Content of an attachment file.
+struct Attachment<'a> {
+    source: String,
+    content: &'a str,
+}
+
+/// Everything written for a single workflow.
+struct Results<'a> {
+    tests: Vec<TestResult<'a>>,
+    suites: Vec<Container<'a>>,
+    workflow: Container<'a>,
+    attachments: Vec<Attachment<'a>>,
+}
+
+fn millis(time: SystemTime) -> Option<u64> {
+    time.duration_since(UNIX_EPOCH).ok().map(|d| d.as_millis() as u64)
+}
+
+impl<'a> Results<'a> {
+    fn new(runspec: &'a Runspec, suites: &'a [TestSuite], run: &str) -> Results<'a> {
+        let mut results = Results {
+            tests: Vec::new(),
+            suites: Vec::new(),
+            workflow: Container {
+                uuid: guid(&[run, &runspec.name]),
+                name: &runspec.name,
+                children: Vec::new(),
+                start: None,
+                stop: None,
+            },
+            attachments: Vec::new(),
+        };
+        for suite in suites {
+            let span = suite.span();
+            let mut container = Container {
+                uuid: guid(&[run, &suite.name]),
+                name: &suite.name,
+                children: Vec::new(),
+                start: span.and_then(|(start, _)| millis(start)),
+                stop: span.and_then(|(_, stop)| millis(stop)),
+            };
+            for case in &suite.test_cases {
+                let test = results.test_result(runspec, suite, case, run);
+                container.children.push(test.uuid.clone());
+                results.tests.push(test);
+            }
+            results.workflow.children.extend(container.children.iter().cloned());
+            results.workflow.start = results.workflow.start.into_iter().chain(container.start).min();
+            results.workflow.stop = results.workflow.stop.into_iter().chain(container.stop).max();
+            results.suites.push(container);
+        }
+        results
+    }
+
+    fn test_result(&mut self, runspec: &'a Runspec, suite: &'a TestSuite, case: &'a TestCase, run: &str) -> TestResult<'a> {
+        let uuid = guid(&[run, &suite.name, &case.name]);
+        let mut labels = vec![
+            Label { name: "parentSuite", value: &runspec.name },
+            Label { name: "suite", value: &suite.name },
+            Label { name: "framework", value: "libtest" },
+            Label { name: "language", value: "rust" },
+        ];
+        labels.extend(runspec.features.iter().map(|feature| Label { name: "tag", value: feature }));
+        let features = if runspec.features.is_empty() {
+            String::from("default")
+        } else {
+            runspec.features.join(", ")
+        };
+        let parameters = vec![
+            Parameter { name: "workflow", value: runspec.name.clone() },
+            Parameter { name: "features", value: features },
+        ];
+
+        let (status, status_details, output) = if let Some(ref failure) = case.failure {
+            let details = StatusDetails {
+                message: summarize_failure(&failure.message),
+                trace: Some(&failure.message),
+            };
+            ("failed", Some(details), Some(("stdout", failure.message.as_str())))
+        } else if let Some(ref error) = case.error {
+            let details = StatusDetails {
+                message: error.message.clone(),
+                trace: error.output.as_deref(),
+            };
+            ("broken", Some(details), error.output.as_deref().map(|stderr| ("stderr", stderr)))
+        } else if let Some(ref skipped) = case.skipped {
+            let details = skipped.message.clone().map(|message| StatusDetails { message, trace: None });
+            ("skipped", details, None)
+        } else {
+            ("passed", None, None)
+        };
+        let mut attachments = Vec::new();
+        if let Some((name, content)) = output {
+            let source = format!("{}-attachment.txt", guid(&[run, &suite.name, &case.name, name]));
+            attachments.push(AttachmentRef {
+                name,
+                source: source.clone(),
+                content_type: "text/plain",
+            });
+            self.attachments.push(Attachment { source, content });
+        }
+
+        // libtest runs tests in parallel and doesn't say when each one started.
+        let start = suite.timestamp;
+        let stop = start.map(|start| start + case.time.unwrap_or_default());
+        TestResult {
+            uuid,
+            history_id: guid(&[&suite.name, &case.name]),
+            name: &case.name,
+            full_name: format!("{} {}", suite.name, case.name),
+            status,
+            status_details,
+            stage: "finished",
+            start: start.and_then(millis),
+            stop: stop.and_then(millis),
+            labels,
+            parameters,
+            attachments,
+        }
+    }
+
+    fn write_to(&self, dir: &Path) -> io::Result<()> {
+        for test in &self.tests {
+            write_json(&dir.join(format!("{}-result.json", test.uuid)), test)?;
+        }
+        for container in self.suites.iter().chain(Some(&self.workflow)) {
+            write_json(&dir.join(format!("{}-container.json", container.uuid)), container)?;
+        }
+        for attachment in &self.attachments {
+            let mut file = create_file(&dir.join(&attachment.source))?;
+            file.write_all(attachment.content.as_bytes())?;
+            file.flush()?;
+        }
+        Ok(())
+    }
+}
+
+fn write_json<T: serde::Serialize>(path: &Path, value: &T) -> io::Result<()> {
+    let mut file = create_file(path)?;
+    serde_json::to_writer_pretty(&mut file, value).map_err(io::Error::from)?;
+    file.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Results;
+    use crate::junit::crashed_suite;
+    use crate::runspec::Runspec;
+    use serde_json::{json, to_value};
+
+    #[test]
+    fn results_and_containers() {
+        let suite = crashed_suite();
+        let runspec = Runspec {
+            name: String::from("default"),
+            features: vec![String::from("serde")],
+            ..Runspec::default()
+        };
+        let suites = [suite];
+
+        let results = Results::new(&runspec, &suites, "run");
+        assert_eq!(4, results.tests.len());
+        assert_eq!(1, results.suites.len());
+
+        let works = to_value(&results.tests[0]).unwrap();
+        assert_eq!("passed", works["status"]);
+        // Ids must not change with the toolchain, or Allure loses the history of every test.
+        assert_eq!("fc93e2fb-9dec-88f1-b53b-d744f14f77d9", works["historyId"]);
+        assert_eq!("94704641-403f-801d-85be-42cf89973063", works["uuid"]);
+        assert_eq!(1_600_000_000_000u64, works["start"]);
+        assert_eq!(1_600_000_000_250u64, works["stop"]);
+        let labels = json!([
+            { "name": "parentSuite", "value": "default" },
+            { "name": "suite", "value": "[default] Lib-tests" },
+            { "name": "framework", "value": "libtest" },
+            { "name": "language", "value": "rust" },
+            { "name": "tag", "value": "serde" }
+        ]);
+        assert_eq!(labels, works["labels"]);
+        let parameters = json!([{ "name": "workflow", "value": "default" }, { "name": "features", "value": "serde" }]);
+        assert_eq!(parameters, works["parameters"]);
+
+        let broken = to_value(&results.tests[1]).unwrap();
+        assert_eq!("failed", broken["status"]);
+        assert_eq!("boom", broken["statusDetails"]["message"]);
+        assert_eq!("stdout", broken["attachments"][0]["name"]);
+        assert_eq!(2, results.attachments.len());
+        assert_eq!(broken["attachments"][0]["source"], results.attachments[0].source.as_str());
+        assert!(results.attachments[0].content.contains("panicked at 'boom'"));
+
+        let needs_zfs = to_value(&results.tests[2]).unwrap();
+        assert_eq!("skipped", needs_zfs["status"]);
+        assert_eq!("requires root", needs_zfs["statusDetails"]["message"]);
+
+        let segfaults = to_value(&results.tests[3]).unwrap();
+        assert_eq!("broken", segfaults["status"]);
+        assert_eq!("terminated by signal 11 (SIGSEGV)", segfaults["statusDetails"]["message"]);
+        assert_eq!("stderr", segfaults["attachments"][0]["name"]);
+        assert_eq!("oops", results.attachments[1].content);
+
+        // History is kept across runs, results themselves are not overwritten.
+        let next_run = Results::new(&runspec, &suites, "next run");
+        assert_eq!(works["historyId"], next_run.tests[0].history_id.as_str());
+        assert_ne!(works["uuid"], next_run.tests[0].uuid.as_str());
+
+        let suite = to_value(&results.suites[0]).unwrap();
+        assert_eq!(4, suite["children"].as_array().unwrap().len());
+        assert_eq!(1_600_000_000_750u64, suite["stop"]);
+        let workflow = to_value(&results.workflow).unwrap();
+        assert_eq!("default", workflow["name"]);
+        assert_eq!(suite["children"], workflow["children"]);
+    }
+}
@@ -0,0 +1,117 @@
+//! Support for cargo's `--message-format=json` build output.
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// Build target that produced an artifact.
+#[derive(Deserialize, Debug, Eq, PartialEq)]
+pub struct Target {
+    /// Name of the target, e.g. file stem of integration test.
+    pub name: String,
+    /// Kinds of the target: `lib`, `bin`, `test`, `example`, `bench`, etc.
+    pub kind: Vec<String>,
+}
+
+/// Build profile of an artifact.
+#[derive(Deserialize, Debug, Default, Eq, PartialEq)]
+pub struct Profile {
+    /// Whether target was built with test harness.
+    pub test: bool,
+}
+
+/// `compiler-artifact` message emitted by cargo for every compiled target.
+#[derive(Deserialize, Debug, Eq, PartialEq)]
+pub struct Artifact {
+    /// Package the target belongs to, matches `id` in `cargo metadata`.
+    pub package_id: String,
+    pub target: Target,
+    #[serde(default)]
+    pub profile: Profile,
+    /// Path to produced executable, if target produced one.
+    pub executable: Option<PathBuf>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "reason", rename_all = "kebab-case")]
+enum Message {
+    CompilerArtifact(Artifact),
+    #[serde(other)]
+    Other,
+}
+
+impl Artifact {
+    pub fn has_kind(&self, kind: &str) -> bool {
+        self.target.kind.iter().any(|k| k == kind)
+    }
+}
+
+/// Parse cargo's JSON messages and return every compiled artifact. Lines that are not JSON
+/// messages are skipped.
+pub fn parse_artifacts(stdout: &str) -> Vec<Artifact> {
+    stdout
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .filter_map(|message| match message {
+            Message::CompilerArtifact(artifact) => Some(artifact),
+            Message::Other => None,
+        })
+        .collect()
+}
+
+/// Map names of targets of `kind` built with test harness to their executables. Binaries and
+/// examples are also built without it, those are skipped.
+pub fn test_executables<'a, I: IntoIterator<Item = &'a Artifact>>(artifacts: I, kind: &str) -> BTreeMap<String, PathBuf> {
+    artifacts
+        .into_iter()
+        .filter(|artifact| artifact.has_kind(kind) && artifact.profile.test)
+        .filter_map(|artifact| {
+            artifact
+                .executable
+                .clone()
+                .map(|executable| (artifact.target.name.clone(), executable))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_artifacts, test_executables};
+    use std::path::PathBuf;
+
+    const STDOUT: &str = r#"{"reason":"compiler-artifact","package_id":"serde 1.0.0","target":{"kind":["lib"],"crate_types":["lib"],"name":"serde","src_path":"/serde/src/lib.rs","edition":"2015","doctest":true,"test":true},"profile":{"opt_level":"0","debuginfo":2,"debug_assertions":true,"overflow_checks":true,"test":false},"features":[],"filenames":["/target/debug/deps/libserde.rlib"],"executable":null,"fresh":true}
+{"reason":"compiler-artifact","package_id":"app 0.1.0","target":{"kind":["lib"],"crate_types":["lib"],"name":"app","src_path":"/app/src/lib.rs","edition":"2018","doctest":true,"test":true},"profile":{"opt_level":"0","debuginfo":2,"debug_assertions":true,"overflow_checks":true,"test":true},"features":[],"filenames":["/target/debug/deps/app-1111"],"executable":"/target/debug/deps/app-1111","fresh":false}
+{"reason":"compiler-artifact","package_id":"app 0.1.0","target":{"kind":["test"],"crate_types":["bin"],"name":"api","src_path":"/app/tests/api.rs","edition":"2018","doctest":false,"test":true},"profile":{"opt_level":"0","debuginfo":2,"debug_assertions":true,"overflow_checks":true,"test":true},"features":[],"filenames":["/target/debug/deps/api-2222"],"executable":"/target/debug/deps/api-2222","fresh":false}
+{"reason":"compiler-artifact","package_id":"app 0.1.0","target":{"kind":["test"],"crate_types":["bin"],"name":"api_v2","src_path":"/app/tests/api_v2.rs","edition":"2018","doctest":false,"test":true},"profile":{"opt_level":"0","debuginfo":2,"debug_assertions":true,"overflow_checks":true,"test":true},"features":[],"filenames":["/target/debug/deps/api_v2-3333"],"executable":"/target/debug/deps/api_v2-3333","fresh":false}
+{"reason":"build-finished","success":true}"#;
+
+    #[test]
+    fn parse_compiler_artifacts() {
+        let artifacts = parse_artifacts(STDOUT);
+        assert_eq!(4, artifacts.len());
+        assert!(artifacts[0].executable.is_none());
+        assert!(artifacts[2].has_kind("test"));
+    }
+
+    #[test]
+    fn map_integration_tests_to_executables() {
+        let artifacts = parse_artifacts(STDOUT);
+        let tests = test_executables(&artifacts, "test");
+
+        assert_eq!(2, tests.len());
+        assert_eq!(Some(&PathBuf::from("/target/debug/deps/api-2222")), tests.get("api"));
+        assert_eq!(Some(&PathBuf::from("/target/debug/deps/api_v2-3333")), tests.get("api_v2"));
+    }
+
+    #[test]
+    fn skip_executables_built_without_harness() {
+        let stdout = r#"{"reason":"compiler-artifact","package_id":"app 0.1.0","target":{"kind":["bin"],"name":"tool"},"profile":{"test":true},"executable":"/target/debug/deps/tool-4444"}
+{"reason":"compiler-artifact","package_id":"app 0.1.0","target":{"kind":["bin"],"name":"tool"},"profile":{"test":false},"executable":"/target/debug/tool"}
+{"reason":"compiler-artifact","package_id":"app 0.1.0","target":{"kind":["example"],"name":"demo"},"profile":{"test":false},"executable":"/target/debug/examples/demo"}"#;
+        let artifacts = parse_artifacts(stdout);
+
+        let bins = test_executables(&artifacts, "bin");
+        assert_eq!(1, bins.len());
+        assert_eq!(Some(&PathBuf::from("/target/debug/deps/tool-4444")), bins.get("tool"));
+        assert!(test_executables(&artifacts, "example").is_empty());
+    }
+}
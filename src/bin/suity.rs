@@ -1,112 +1,256 @@
-//#[macro_use]
-//extern crate structopt;
-//use structopt::StructOpt;
+use structopt::StructOpt;
 use colored::*;
-use std::{io,fs,path, process};
+use std::{fs, path, process};
 use cargo_suity as lib;
-use lib::runspec::{RunspecResult};
+use lib::runspec::{ExitCodePolicy, OutputFormat, Runspec, INTERNAL_ERROR_EXIT_CODE};
 use lib::errors::SuityError;
 use lib::configuration;
-/*
-/// A basic example
+use lib::junit::{TestCase, TestSuite};
+use lib::listener::Listener;
+use lib::manifest::Manifest;
+use lib::report::{self, WorkflowResults};
+use lib::scheduler;
+use lib::summary::{Summary, WorkflowSummary};
+
+#[derive(StructOpt, Debug)]
+#[structopt(bin_name = "cargo")]
+enum Cargo {
+    /// Test runner and reporter for cargo.
+    #[structopt(name = "suity")]
+    Suity(Suity),
+}
+
+/// Test runner and reporter for cargo.
 #[derive(StructOpt, Debug)]
-#[structopt(name = "cargo suity", about = "Test runner and reporter for cargo.")]
 struct Suity {
-    /// Names of workflows to run. Workflows are defined in suity.toml. If unspecified - run default one. If set to "*" - runs all.
+    /// Names of workflows to run. Workflows are defined in suity.toml. If unspecified or set to "*" - runs all.
     pub workflows: Vec<String>,
+    /// Path to configuration file. Default `suity.toml` if it exists, then `package.metadata.suity` or
+    /// `workspace.metadata.suity` of Cargo.toml.
+    #[structopt(long = "config", parse(from_os_str))]
+    pub config: Option<path::PathBuf>,
+    /// Override output directory of selected workflows.
+    #[structopt(long = "output", parse(from_os_str))]
+    pub output: Option<path::PathBuf>,
+    /// Override list of features of selected workflows.
+    #[structopt(long = "features", use_delimiter = true, require_delimiter = true)]
+    pub features: Option<Vec<String>>,
+    /// Override report formats of selected workflows (comma separated).
+    #[structopt(long = "format", use_delimiter = true, require_delimiter = true)]
+    pub format: Option<Vec<OutputFormat>>,
+    /// How many test processes to run at once, 0 means one per CPU. Overrides `run.jobs`.
+    #[structopt(long = "jobs", short = "j")]
+    pub jobs: Option<usize>,
+    /// How to compute exit code: `status` or `count`. Overrides `run.exit_code`.
+    #[structopt(long = "exit-code")]
+    pub exit_code: Option<ExitCodePolicy>,
+}
+
+impl Suity {
+    /// Command line overrides expressed as a workflow, so they merge like any other workflow.
+    fn as_overrides(&self) -> configuration::Workflow {
+        configuration::Workflow {
+            features: self.features.clone(),
+            format: self.format.clone(),
+            output: self.output.clone(),
+            ..configuration::Workflow::default()
+        }
+    }
 }
-*/
+
 fn main() {
-    //let opt = Suity::from_args();
-    match run_whole_thing() {
+    let Cargo::Suity(opt) = Cargo::from_args();
+    match run_whole_thing(&opt) {
         Ok(code) => process::exit(code),
         Err(e) => {
             eprintln!("Ran into error: {}", e);
-            process::exit(101)
+            process::exit(INTERNAL_ERROR_EXIT_CODE)
         }
     }
 
 }
 
-fn run_whole_thing() -> Result<i32, SuityError> {
-    let configuration = get_configuration()?;
-    let mut exit_code = 0;
+fn run_whole_thing(opt: &Suity) -> Result<i32, SuityError> {
+    let configuration = get_configuration(opt.config.as_deref())?;
+    let overrides = opt.as_overrides();
+    let concurrency = scheduler::effective_concurrency(opt.jobs.or(configuration.run.jobs).unwrap_or(1));
+    let mut specs: Vec<Runspec> = Vec::new();
+    for spec in configuration.select_runspecs(&opt.workflows)? {
+        let spec = overrides.clone().merge(spec.name.clone(), &spec);
+        specs.extend(spec.expand_feature_matrix(path::Path::new("Cargo.toml"))?);
+    }
+
+    // Build everything first, then run test processes of all workflows together. Workflow that
+    // didn't compile is reported as such, the rest still run.
+    let mut jobs = Vec::new();
+    let mut jobs_per_spec = Vec::with_capacity(specs.len());
+    let mut listeners = Vec::new();
+    for spec in &specs {
+        match spec.prepare() {
+            Ok(spec_jobs) => {
+                listeners.extend(spec.listeners(&spec_jobs));
+                jobs_per_spec.push(Some(spec_jobs.len()));
+                jobs.extend(spec_jobs);
+            }
+            Err(e @ SuityError::FailedToCompile { .. }) => {
+                eprintln!(" {} {}\n", "FAIL".red(), e);
+                jobs_per_spec.push(None);
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    let console = Console { parallel: concurrency > 1 };
+    let mut results = scheduler::run_all(jobs, concurrency, &mut (console, listeners)).into_iter();
 
-    for mut spec in configuration.get_runspecs() {
-        exit_code += execute_runspec(&mut spec).map(|r| r.as_exit_code())?;
+    let mut suites_per_spec = Vec::with_capacity(specs.len());
+    let mut workflows = Vec::with_capacity(specs.len());
+    for (spec, count) in specs.iter().zip(jobs_per_spec) {
+        let count = match count {
+            Some(count) => count,
+            None => {
+                workflows.push(WorkflowSummary::build_failed(spec.name.clone()));
+                suites_per_spec.push(None);
+                continue;
+            }
+        };
+        let suites = results.by_ref().take(count).collect::<Result<Vec<_>, _>>()?;
+        let suites: Vec<TestSuite> = suites.into_iter().flatten().collect();
+        report_runspec(spec, &suites)?;
+        workflows.push(WorkflowSummary::new(spec.name.clone(), &suites));
+        suites_per_spec.push(Some(suites));
     }
-    Ok(exit_code)
+
+    let output = opt.output.as_ref().unwrap_or(&configuration.global.output);
+    let run: Vec<WorkflowResults> = specs
+        .iter()
+        .zip(&suites_per_spec)
+        .map(|(runspec, suites)| WorkflowResults { runspec, suites: suites.as_deref() })
+        .collect();
+    report::report_run(output, &configuration.run, &run)?;
+
+    let policy = opt.exit_code.unwrap_or(configuration.run.exit_code);
+    let summary = Summary::new(workflows, policy);
+    summary.write_as_json(report::create_file(&output.join("summary.json"))?)?;
+    Ok(summary.exit_code)
 
 }
 
-fn get_configuration() -> Result<configuration::Configuration, SuityError> {
-    let conf_file =  path::Path::new("suity.toml");
+fn get_configuration(conf_file: Option<&path::Path>) -> Result<configuration::Configuration, SuityError> {
+    let (conf_file, required) = match conf_file {
+        Some(conf_file) => (conf_file, true),
+        None => (path::Path::new("suity.toml"), false),
+    };
 
-    if conf_file.exists() {
+    if required || conf_file.exists() {
         let contents = fs::read_to_string(conf_file)?;
-        let conf: configuration::Configuration = toml::from_str(&contents).map_err(|e| SuityError::FailedToParseConfiguration(e))?;
-        Ok(conf)
-    } else {
-        Ok(configuration::Configuration::default())
+        let conf: configuration::Configuration = toml::from_str(&contents).map_err(SuityError::FailedToParseConfiguration)?;
+        return Ok(conf);
+    }
+    // Without suity.toml configuration may live in Cargo.toml.
+    let manifest = path::Path::new("Cargo.toml");
+    if manifest.exists() {
+        if let Some(conf) = configuration::Configuration::from_manifest(&Manifest::from_path(manifest)?)? {
+            return Ok(conf);
+        }
     }
+    Ok(configuration::Configuration::default())
 }
 
-fn execute_runspec(runspec: &mut lib::runspec::Runspec) -> Result<RunspecResult, SuityError> {
-    let buf_writer= get_writer(runspec.get_output_file_path())?;
-    let result = runspec.execute(buf_writer).expect("Failed to run default configuration");
-    print_results(&runspec, &result);
-    let total_number_of_failed: u64 = result.iter().map(|s| s.failures).sum();
-    if total_number_of_failed > 0 {
-        Ok(RunspecResult::Errors(total_number_of_failed))
-    } else {
-        Ok(RunspecResult::Ok)
+fn report_runspec(runspec: &Runspec, result: &[TestSuite]) -> Result<(), SuityError> {
+    runspec.write_reports(result)?;
+    print_results(runspec, result);
+    Ok(())
+}
+
+/// Prints progress to stderr as tests finish. When suites run in parallel their output interleaves,
+/// so every test is prefixed with name of its suite.
+struct Console {
+    parallel: bool,
+}
+
+impl Console {
+    fn prefix<'a>(&self, suite: &'a str) -> Option<&'a str> {
+        Some(suite).filter(|_| self.parallel)
     }
 }
 
-fn get_writer(path: path::PathBuf) -> Result<io::BufWriter<fs::File>, lib::errors::SuityError> {
-    if let Some(parent) = path.parent() {
-        if ! parent.exists() {
-            fs::create_dir(parent)?;
+impl Listener for Console {
+    fn suite_started(&mut self, suite: &str) {
+        eprintln!(" {} {}", "RUNS".cyan(), suite);
+    }
+
+    fn test_finished(&mut self, suite: &str, case: &TestCase) {
+        print_test_case(self.prefix(suite), case);
+    }
+
+    fn suite_finished(&mut self, suite: &TestSuite) {
+        // Errors are only known once process exits, they never go through test_finished.
+        for case in suite.test_cases.iter().filter(|case| case.error.is_some()) {
+            print_test_case(self.prefix(&suite.name), case);
+        }
+        let pass_or_fail = if suite.failures == 0 && suite.errors == 0 {
+            "PASS".green()
+        } else {
+            "FAIL".red()
+        };
+        eprintln!(" {} {}", pass_or_fail, &suite.name);
+        if !self.parallel {
+            eprintln!();
         }
     }
-    let file = fs::File::create(path)?;
-    Ok(io::BufWriter::new(file))
 }
 
-fn print_results(runspec: &lib::runspec::Runspec, result: &Vec<lib::junit::TestSuite>) {
+fn print_test_case(suite: Option<&str>, case: &TestCase) {
+    let failure = &case.failure;
+    let check_or_cross = if failure.is_some() || case.error.is_some() {
+        "☓".red()
+    } else if case.skipped.is_some() {
+        "-".yellow()
+    } else {
+        "✓".green()
+    };
+    match suite {
+        Some(suite) => eprintln!("    {} {} {}", check_or_cross, suite, case.name),
+        None => eprintln!("    {} {}", check_or_cross, case.name),
+    }
+    if let Some(ref failure) = failure {
+        for line in failure.message.lines() {
+            eprintln!("        {}", line);
+        }
+    }
+    if let Some(ref error) = case.error {
+        eprintln!("        {}", error.message.red());
+        for line in error.output.iter().flat_map(|output| output.lines()) {
+            eprintln!("        {}", line);
+        }
+    }
+    if let Some(message) = case.skipped.as_ref().and_then(|s| s.message.as_ref()) {
+        eprintln!("        {}", message.yellow());
+    }
+}
+
+fn print_results(runspec: &Runspec, result: &[TestSuite]) {
     let total_number_of_tests: u64 = result.iter().map(|s| s.tests).sum();
-    let total_number_of_failed: u64 = result.iter().map(|s| s.failures).sum();
+    let total_number_of_failed: u64 = result.iter().map(|s| s.failures + s.errors).sum();
+    let total_number_of_skipped: u64 = result.iter().map(|s| s.skipped).sum();
     let total_number_of_failed_str = if total_number_of_failed == 0 {
         "0".green()
     } else {
         total_number_of_failed.to_string().red()
     };
+    let total_number_of_skipped_str = if total_number_of_skipped == 0 {
+        "0".normal()
+    } else {
+        total_number_of_skipped.to_string().yellow()
+    };
     eprintln!("> Workflow:                                  {}", &runspec.name);
     eprintln!("> Total number of tests in workflow:         {}", &total_number_of_tests);
     eprintln!("> Total number of failed tests in workflow:  {}", &total_number_of_failed_str);
-    eprintln!();
+    eprintln!("> Total number of skipped tests in workflow: {}", &total_number_of_skipped_str);
     for suite in result {
-        let pass_or_fail = if suite.failures == 0 {
-            "PASS".green()
-        } else {
-            "FAIL".red()
-        };
-        eprintln!(" {} {}", pass_or_fail, &suite.name);
-
-        for case in &suite.test_cases {
-            let failure = &case.failure;
-            let check_or_cross = if failure.is_some() {
-                "☓".red()
-            } else {
-                "✓".green()
-            };
-            eprintln!("    {} {}", check_or_cross, case.name);
-            if let Some(ref failure) = failure {
-                for line in failure.message.lines() {
-                    eprintln!("        {}", line);
-                }
-            }
+        for case in suite.test_cases.iter().filter(|case| case.failure.is_some() || case.error.is_some()) {
+            eprintln!("    {} {} {}", "☓".red(), &suite.name, case.name);
         }
-        eprintln!();
     }
+    eprintln!();
 }
@@ -1,69 +1,368 @@
 use std::default::Default;
 use std::path::PathBuf;
-use std::collections::HashMap;
-use crate::runspec::{OutputFormat,Runspec};
+use std::collections::BTreeMap;
+use crate::runspec::{formats, targets, ExitCodePolicy, FeatureMatrix, OutputFormat, Runspec};
+use crate::errors::SuityError;
+use crate::manifest::Manifest;
 
 #[derive(Debug, Default, Clone,Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct Workflow {
     /// Override workflow name
     pub name: Option<String>,
+    /// Workflow to take unset values from.
+    pub extends: Option<String>,
     /// List of features to pass to cargo.
     pub features: Option<Vec<String>>,
-    /// Report format.
-    pub format: Option<OutputFormat>,
+    /// Build with crate's default features. Default true.
+    pub default_features: Option<bool>,
+    /// Build with all features of the crate. Default false.
+    pub all_features: Option<bool>,
+    /// Run workflow once per feature combination.
+    pub feature_matrix: Option<FeatureMatrix>,
+    /// Members of the workspace to test.
+    pub packages: Option<Vec<String>>,
+    /// Test every member of the workspace.
+    pub workspace: Option<bool>,
+    /// Report formats.
+    #[serde(default, deserialize_with = "formats::deserialize_option")]
+    pub format: Option<Vec<OutputFormat>>,
     /// Output directory. Default `./test-results/`
     pub output: Option<PathBuf>,
     /// Run Doc-Tests or not. Default true.
     pub doc: Option<bool>,
     /// Run Unit-Tests or not. Default true.
+    #[serde(alias = "lib")]
     pub unit: Option<bool>,
     /// List of integration tests to run. Default all of them.
-    pub integration: Option<Vec<String>>
+    pub integration: Option<Vec<String>>,
+    /// Binaries to run unit tests of, `true` for all of them.
+    #[serde(default, deserialize_with = "targets::deserialize_option")]
+    pub bins: Option<Vec<String>>,
+    /// Examples to run tests of, `true` for all of them.
+    #[serde(default, deserialize_with = "targets::deserialize_option")]
+    pub examples: Option<Vec<String>>,
+    /// Benches to run in test mode, `true` for all of them.
+    #[serde(default, deserialize_with = "targets::deserialize_option")]
+    pub benches: Option<Vec<String>>,
+    /// Attach unparseable test output to tests instead of failing. Default true.
+    pub lenient: Option<bool>,
+    /// Time limit in seconds for each test suite.
+    pub timeout: Option<u64>,
+    /// Time limit in seconds for each test.
+    pub test_timeout: Option<u64>,
 }
 
 impl Workflow {
+    /// Take values that are not set in this workflow from `parent`. Name is not inherited, lists
+    /// are replaced as a whole.
+    pub fn inherit(self, parent: &Workflow) -> Workflow {
+        Workflow {
+            name: self.name,
+            extends: parent.extends.clone(),
+            features: self.features.or_else(|| parent.features.clone()),
+            default_features: self.default_features.or(parent.default_features),
+            all_features: self.all_features.or(parent.all_features),
+            feature_matrix: self.feature_matrix.or_else(|| parent.feature_matrix.clone()),
+            packages: self.packages.or_else(|| parent.packages.clone()),
+            workspace: self.workspace.or(parent.workspace),
+            format: self.format.or_else(|| parent.format.clone()),
+            output: self.output.or_else(|| parent.output.clone()),
+            doc: self.doc.or(parent.doc),
+            unit: self.unit.or(parent.unit),
+            integration: self.integration.or_else(|| parent.integration.clone()),
+            bins: self.bins.or_else(|| parent.bins.clone()),
+            examples: self.examples.or_else(|| parent.examples.clone()),
+            benches: self.benches.or_else(|| parent.benches.clone()),
+            lenient: self.lenient.or(parent.lenient),
+            timeout: self.timeout.or(parent.timeout),
+            test_timeout: self.test_timeout.or(parent.test_timeout),
+        }
+    }
+
     /// Merge default run configuration and defined workflow.
     pub fn merge(self, name: String, right: &Runspec) -> Runspec {
         Runspec {
             name: self.name.unwrap_or(name),
             features: self.features.unwrap_or(right.features.clone()),
-            format: self.format.unwrap_or(right.format),
+            default_features: self.default_features.unwrap_or(right.default_features),
+            all_features: self.all_features.unwrap_or(right.all_features),
+            feature_matrix: self.feature_matrix.or(right.feature_matrix.clone()),
+            packages: self.packages.unwrap_or(right.packages.clone()),
+            workspace: self.workspace.unwrap_or(right.workspace),
+            format: self.format.unwrap_or(right.format.clone()),
             output: self.output.unwrap_or(right.output.clone()),
             doc: self.doc.unwrap_or(right.doc),
             lib: self.unit.unwrap_or(right.lib),
             integration: self.integration.unwrap_or(right.integration.clone()),
+            bins: self.bins.unwrap_or(right.bins.clone()),
+            examples: self.examples.unwrap_or(right.examples.clone()),
+            benches: self.benches.unwrap_or(right.benches.clone()),
+            lenient: self.lenient.unwrap_or(right.lenient),
+            timeout: self.timeout.or(right.timeout),
+            test_timeout: self.test_timeout.or(right.test_timeout),
         }
     }
 }
 
+/// Settings of the whole run rather than of a single workflow.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RunSettings {
+    /// How many test processes to run at once, 0 means one per CPU. Default 1.
+    pub jobs: Option<usize>,
+    /// How to compute exit code. Default `status`.
+    #[serde(default)]
+    pub exit_code: ExitCodePolicy,
+    /// Environment variable naming a file Markdown summary is appended to, e.g.
+    /// `GITHUB_STEP_SUMMARY`. Default none.
+    pub step_summary_env: Option<String>,
+}
+
 #[derive(Debug,Deserialize,Default)]
+#[serde(deny_unknown_fields)]
 pub struct Configuration {
+    #[serde(default)]
+    pub run: RunSettings,
+    #[serde(default)]
     pub global: Runspec,
-    pub workflow: HashMap<String, Workflow>,
+    /// Workflows by name, kept sorted so runs and reports list them in a stable order.
+    #[serde(default)]
+    pub workflow: BTreeMap<String, Workflow>,
 }
 
 impl Configuration {
+    /// Configuration embedded into Cargo.toml, `None` if there is none. `[package.metadata.suity]`
+    /// takes precedence over `[workspace.metadata.suity]`, they are never merged.
+    pub fn from_manifest(manifest: &Manifest) -> Result<Option<Configuration>, SuityError> {
+        manifest.suity_metadata()
+            .map(|suity| suity.clone().try_into().map_err(SuityError::FailedToParseConfiguration))
+            .transpose()
+    }
+
     /// Return list of fully actionable run configurations aka Runspec.
-    pub fn get_runspecs(&self) -> Vec<Runspec> {
+    pub fn get_runspecs(&self) -> Result<Vec<Runspec>, SuityError> {
         if self.workflow.is_empty() {
-            return vec![self.get_default()];
+            return Ok(vec![self.get_default()?]);
         }
-        self.workflow.iter()
-            .map(|(name,workflow)| (name.clone(), workflow.clone()))
-            .map(|(name, workflow)| workflow.merge(name, &self.global))
+        self.workflow.keys()
+            .map(|name| self.resolve(name).map(|workflow| workflow.merge(name.clone(), &self.global)))
             .collect()
     }
 
-    /// Give specific runspec
-    pub fn get_runspec(&self, name: &String) -> Option<Runspec> {
-        self.workflow.get(name)
-            .map(|s| s.clone().merge(name.clone(), &self.global))
+    /// Give specific runspec, `None` if workflow is not defined.
+    pub fn get_runspec(&self, name: &String) -> Result<Option<Runspec>, SuityError> {
+        if !self.workflow.contains_key(name) {
+            return Ok(None);
+        }
+        self.resolve(name).map(|workflow| Some(workflow.merge(name.clone(), &self.global)))
     }
 
-    pub fn get_default(&self) -> Runspec {
+    /// Resolve workflows by name. Empty list or "*" selects all of them.
+    pub fn select_runspecs(&self, names: &[String]) -> Result<Vec<Runspec>, SuityError> {
+        if names.is_empty() || names.iter().any(|name| name == "*") {
+            return self.get_runspecs();
+        }
+        names.iter()
+            .map(|name| {
+                if name == "default" {
+                    self.get_default()
+                } else {
+                    self.get_runspec(name)?
+                        .ok_or_else(|| SuityError::WorkflowNotFound { name: name.clone() })
+                }
+            })
+            .collect()
+    }
+
+    pub fn get_default(&self) -> Result<Runspec, SuityError> {
         let name = String::from("default");
-        self.get_runspec(&name)
-            .unwrap_or(Workflow::default().merge(name, &self.global))
+        Ok(self.get_runspec(&name)?
+            .unwrap_or_else(|| Workflow::default().merge(name, &self.global)))
+    }
+
+    /// Defined workflow with everything it `extends` folded into it.
+    fn resolve(&self, name: &str) -> Result<Workflow, SuityError> {
+        let mut chain: Vec<&str> = vec![name];
+        let mut workflow = self.workflow[name].clone();
+        while let Some(parent) = workflow.extends.clone() {
+            if chain.contains(&parent.as_str()) {
+                let cycle: Vec<&str> = chain.iter().cloned().chain(Some(parent.as_str())).collect();
+                return Err(SuityError::WorkflowCycle { chain: cycle.join(" -> ") });
+            }
+            let (parent, definition) = self.workflow.get_key_value(&parent)
+                .ok_or_else(|| SuityError::ParentWorkflowNotFound {
+                    name: chain[chain.len() - 1].to_string(),
+                    parent: parent.clone(),
+                })?;
+            chain.push(parent);
+            workflow = workflow.inherit(definition);
+        }
+        Ok(workflow)
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::Configuration;
+    use crate::manifest::Manifest;
+    use crate::runspec::ExitCodePolicy;
+
+    const CONFIG: &str = r#"
+[global]
+features = ["serde"]
+
+[workflow.default]
+doc = false
+
+[workflow.minimal]
+features = []
+"#;
+
+    #[test]
+    fn select_all_workflows() {
+        let conf: Configuration = toml::from_str(CONFIG).unwrap();
+        let names: Vec<String> = conf.select_runspecs(&[]).unwrap().into_iter().map(|s| s.name).collect();
+        assert_eq!(vec!["default", "minimal"], names);
+        assert_eq!(2, conf.select_runspecs(&[String::from("*")]).unwrap().len());
+    }
+
+    #[test]
+    fn select_named_workflow() {
+        let conf: Configuration = toml::from_str(CONFIG).unwrap();
+        let specs = conf.select_runspecs(&[String::from("minimal")]).unwrap();
+        assert_eq!(1, specs.len());
+        assert_eq!("minimal", specs[0].name);
+        assert!(specs[0].features.is_empty());
+    }
+
+    #[test]
+    fn select_unknown_workflow() {
+        let conf: Configuration = toml::from_str(CONFIG).unwrap();
+        assert!(conf.select_runspecs(&[String::from("nope")]).is_err());
+    }
+
+    #[test]
+    fn run_settings() {
+        let run = "[run]\njobs = 0\nexit_code = \"count\"\nstep_summary_env = \"GITHUB_STEP_SUMMARY\"\n";
+        let conf: Configuration = toml::from_str(&format!("{}{}", run, CONFIG)).unwrap();
+        assert_eq!(Some(0), conf.run.jobs);
+        assert_eq!(ExitCodePolicy::Count, conf.run.exit_code);
+        assert_eq!(Some("GITHUB_STEP_SUMMARY"), conf.run.step_summary_env.as_deref());
+
+        let conf: Configuration = toml::from_str(CONFIG).unwrap();
+        assert_eq!(None, conf.run.jobs);
+        assert_eq!(ExitCodePolicy::Status, conf.run.exit_code);
+    }
+
+    #[test]
+    fn run_settings_outside_of_run() {
+        let config = CONFIG.replace("[global]", "[global]\njobs = 4");
+        assert!(toml::from_str::<Configuration>(&config).is_err());
+    }
+
+    #[test]
+    fn unknown_tables_and_keys() {
+        let misspelt_table = CONFIG.replace("[workflow.default]", "[workflows.default]");
+        assert!(toml::from_str::<Configuration>(&misspelt_table).is_err());
+        let misspelt_key = CONFIG.replace("doc = false", "docs = false");
+        assert!(toml::from_str::<Configuration>(&misspelt_key).is_err());
+        // E.g. `--config Cargo.toml`.
+        let manifest = "[package]\nname = \"crashy\"\n\n[dependencies]\n";
+        assert!(toml::from_str::<Configuration>(manifest).is_err());
+    }
+
+    #[test]
+    fn lib_is_unit() {
+        let config = CONFIG.replace("doc = false", "lib = false");
+        let config: Configuration = toml::from_str(&config).unwrap();
+        let default = config.get_runspec(&String::from("default")).unwrap().unwrap();
+        assert!(!default.lib);
+    }
+
+    #[test]
+    fn configuration_from_manifest() {
+        let manifest: Manifest = toml::from_str(
+            r#"
+[package]
+name = "crashy"
+
+[package.metadata.suity.workflow.quick]
+doc = false
+"#,
+        )
+        .unwrap();
+        // `global` is optional, a single workflow is enough.
+        let conf = Configuration::from_manifest(&manifest).unwrap().unwrap();
+        let specs = conf.select_runspecs(&[]).unwrap();
+        assert_eq!(1, specs.len());
+        assert_eq!("quick", specs[0].name);
+        assert!(!specs[0].doc);
+
+        let plain: Manifest = toml::from_str("[package]\nname = \"crashy\"\n").unwrap();
+        assert!(Configuration::from_manifest(&plain).unwrap().is_none());
+
+        let broken: Manifest = toml::from_str("[package.metadata.suity.workflow.quick]\ndoc = 1\n").unwrap();
+        assert!(Configuration::from_manifest(&broken).is_err());
+    }
+
+    const INHERITED: &str = r#"
+[global]
+features = ["serde"]
+
+[workflow.ci-base]
+doc = false
+timeout = 60
+integration = ["api"]
+
+[workflow.ci-tokio]
+extends = "ci-base"
+features = ["tokio"]
+
+[workflow.ci-tokio-slow]
+extends = "ci-tokio"
+timeout = 600
+"#;
+
+    #[test]
+    fn resolve_chain_of_parents() {
+        let conf: Configuration = toml::from_str(INHERITED).unwrap();
+        let spec = conf.get_runspec(&String::from("ci-tokio-slow")).unwrap().unwrap();
+
+        assert_eq!("ci-tokio-slow", spec.name);
+        assert_eq!(vec!["tokio"], spec.features);
+        assert_eq!(Some(600), spec.timeout);
+        assert_eq!(vec!["api"], spec.integration);
+        assert!(!spec.doc);
+        assert_eq!(3, conf.get_runspecs().unwrap().len());
+    }
+
+    #[test]
+    fn missing_parent() {
+        let conf: Configuration = toml::from_str("[workflow.child]\nextends = \"nope\"\n").unwrap();
+        let error = conf.select_runspecs(&[]).unwrap_err();
+        assert_eq!("Workflow child extends nope, which is not defined.", error.to_string());
+    }
+
+    #[test]
+    fn parents_in_a_loop() {
+        let conf: Configuration = toml::from_str(
+            r#"
+[workflow.a]
+extends = "b"
+
+[workflow.b]
+extends = "c"
+
+[workflow.c]
+extends = "a"
+"#,
+        )
+        .unwrap();
+        let error = conf.get_runspec(&String::from("a")).unwrap_err();
+        assert_eq!("Workflows extend each other in a loop: a -> b -> c -> a.", error.to_string());
+
+        let conf: Configuration = toml::from_str("[workflow.a]\nextends = \"a\"\n").unwrap();
+        assert!(conf.get_runspec(&String::from("a")).is_err());
+    }
+}
@@ -0,0 +1,181 @@
+//! Support for export in CTRF (Common Test Report Format) JSON.
+
+use crate::junit::{TestCase, TestSuite};
+use crate::panic::{find_panic, summarize_failure};
+use crate::report::FileReporter;
+use crate::runspec::Runspec;
+use std::io::{self, Write};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Writes CTRF report.
+pub struct CtrfReporter;
+
+impl FileReporter for CtrfReporter {
+    fn extension(&self) -> &'static str {
+        "ctrf.json"
+    }
+
+    fn write(&self, _runspec: &Runspec, suites: &[TestSuite], output: &mut dyn Write) -> io::Result<()> {
+        write_as_ctrf(suites, output)
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Report<'a> {
+    report_format: &'static str,
+    spec_version: &'static str,
+    results: Results<'a>,
+}
+
+#[derive(Serialize)]
+struct Results<'a> {
+    tool: Tool,
+    summary: Summary,
+    tests: Vec<Test<'a>>,
+}
+
+#[derive(Serialize)]
+struct Tool {
+    name: &'static str,
+    version: &'static str,
+}
+
+#[derive(Serialize)]
+struct Summary {
+    tests: usize,
+    passed: usize,
+    failed: usize,
+    skipped: usize,
+    pending: usize,
+    other: usize,
+    /// Milliseconds since epoch.
+    start: u64,
+    stop: u64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Test<'a> {
+    name: &'a str,
+    status: &'static str,
+    /// Milliseconds.
+    duration: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    trace: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    file_path: Option<String>,
+    suite: &'a str,
+}
+
+impl<'a> Test<'a> {
+    fn new(suite: &'a TestSuite, case: &'a TestCase) -> Test<'a> {
+        let mut test = Test {
+            name: &case.name,
+            status: "passed",
+            duration: case.time.map(millis).unwrap_or(0),
+            message: None,
+            trace: None,
+            file_path: None,
+            suite: &suite.name,
+        };
+        if let Some(ref failure) = case.failure {
+            test.status = "failed";
+            test.message = Some(summarize_failure(&failure.message));
+            test.trace = Some(&failure.message);
+            test.file_path = find_panic(&failure.message).map(|panic| panic.file);
+        } else if let Some(ref error) = case.error {
+            test.status = "failed";
+            test.message = Some(error.message.clone());
+            test.trace = error.output.as_deref();
+        } else if let Some(ref skipped) = case.skipped {
+            test.status = "skipped";
+            test.message = skipped.message.clone();
+        }
+        test
+    }
+}
+
+fn millis(duration: Duration) -> u64 {
+    duration.as_millis() as u64
+}
+
+pub fn write_as_ctrf<W: Write>(suites: &[TestSuite], writer: W) -> io::Result<()> {
+    let tests: Vec<Test> = suites
+        .iter()
+        .flat_map(|suite| suite.test_cases.iter().map(move |case| Test::new(suite, case)))
+        .collect();
+    let count = |status: &str| tests.iter().filter(|test| test.status == status).count();
+    let since_epoch = |time: SystemTime| time.duration_since(UNIX_EPOCH).ok().map(millis);
+    let spans: Vec<(u64, u64)> = suites
+        .iter()
+        .filter_map(TestSuite::span)
+        .filter_map(|(start, stop)| Some((since_epoch(start)?, since_epoch(stop)?)))
+        .collect();
+    let now = || since_epoch(SystemTime::now()).unwrap_or(0);
+    let start = spans.iter().map(|(start, _)| *start).min().unwrap_or_else(now);
+    let stop = spans.iter().map(|(_, stop)| *stop).max().unwrap_or(start);
+
+    let report = Report {
+        report_format: "CTRF",
+        spec_version: "0.0.0",
+        results: Results {
+            tool: Tool {
+                name: env!("CARGO_PKG_NAME"),
+                version: env!("CARGO_PKG_VERSION"),
+            },
+            summary: Summary {
+                tests: tests.len(),
+                passed: count("passed"),
+                failed: count("failed"),
+                skipped: count("skipped"),
+                pending: 0,
+                other: 0,
+                start,
+                stop,
+            },
+            tests,
+        },
+    };
+    serde_json::to_writer_pretty(writer, &report).map_err(io::Error::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::write_as_ctrf;
+    use crate::junit::crashed_suite;
+    use serde_json::{json, Value};
+
+    #[test]
+    fn report() {
+        let suite = crashed_suite();
+
+        let mut output = Vec::new();
+        write_as_ctrf(&[suite], &mut output).unwrap();
+        let report: Value = serde_json::from_slice(&output).unwrap();
+
+        assert_eq!("CTRF", report["reportFormat"]);
+        assert_eq!("cargo-suity", report["results"]["tool"]["name"]);
+        let summary = json!({
+            "tests": 4, "passed": 1, "failed": 2, "skipped": 1, "pending": 0, "other": 0,
+            "start": 1_600_000_000_000u64, "stop": 1_600_000_000_750u64
+        });
+        assert_eq!(summary, report["results"]["summary"]);
+        let tests = json!([
+            { "name": "works", "status": "passed", "duration": 250, "suite": "[default] Lib-tests" },
+            {
+                "name": "broken", "status": "failed", "duration": 500, "message": "boom",
+                "trace": "thread 'broken' panicked at 'boom', src/lib.rs:7:5\n", "filePath": "src/lib.rs",
+                "suite": "[default] Lib-tests"
+            },
+            { "name": "needs_zfs", "status": "skipped", "duration": 0, "message": "requires root", "suite": "[default] Lib-tests" },
+            {
+                "name": "segfaults", "status": "failed", "duration": 0, "message": "terminated by signal 11 (SIGSEGV)",
+                "trace": "oops", "suite": "[default] Lib-tests"
+            }
+        ]);
+        assert_eq!(tests, report["results"]["tests"]);
+    }
+}
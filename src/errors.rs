@@ -1,3 +1,5 @@
+// failure_derive predates the non_local_definitions lint.
+#![allow(non_local_definitions)]
 use std::io;
 use std::convert::From;
 
@@ -5,6 +7,11 @@ use std::convert::From;
 pub enum SuityError {
     #[fail(display="Event stream contains results for multiple runs.")]
     MultipleTestRuns,
+    #[fail(display="Failed to parse line {} of test output: {}", line, text)]
+    MalformedTestOutput {
+        line: usize,
+        text: String,
+    },
     #[fail(display="Couldn't locate binary for {} in {} workflow.", name, workflow)]
     TestBinaryNotFound {
         name: String,
@@ -16,8 +23,32 @@ pub enum SuityError {
     FailedToCompile {
         workflow: String
     },
+    #[fail(display="Workflow {} is not defined.", name)]
+    WorkflowNotFound {
+        name: String,
+    },
+    #[fail(display="Workflow {} extends {}, which is not defined.", name, parent)]
+    ParentWorkflowNotFound {
+        name: String,
+        parent: String,
+    },
+    #[fail(display="Workflows extend each other in a loop: {}.", chain)]
+    WorkflowCycle {
+        chain: String,
+    },
     #[fail(display="Failed to parse configuration file. See cause for more information.")]
     FailedToParseConfiguration(#[fail(cause)] toml::de::Error),
+    #[fail(display="Failed to parse Cargo.toml. See cause for more information.")]
+    FailedToParseManifest(#[fail(cause)] toml::de::Error),
+    #[fail(display="Failed to read cargo metadata: {}", message)]
+    FailedToReadMetadata {
+        message: String,
+    },
+    #[fail(display="Package {} used in {} workflow is not a member of the workspace.", name, workflow)]
+    PackageNotFound {
+        name: String,
+        workflow: String,
+    },
 }
 
 impl From<io::Error> for SuityError {
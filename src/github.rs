@@ -0,0 +1,140 @@
+//! Support for GitHub Actions workflow commands, so failed tests show up as annotations on a diff.
+
+use crate::errors::SuityError;
+use crate::junit::{TestCase, TestSuite};
+use crate::panic::find_panic;
+use crate::report::Reporter;
+use crate::runspec::Runspec;
+use std::fmt;
+use std::io::{self, Write};
+
+/// Prints `::error` workflow command for every failed test to stdout.
+pub struct GitHubReporter;
+
+impl Reporter for GitHubReporter {
+    /// Workflow commands are only understood on stdout of a job, nothing is written to `output`.
+    fn report(&self, _runspec: &Runspec, suites: &[TestSuite]) -> Result<(), SuityError> {
+        let stdout = io::stdout();
+        let mut stdout = stdout.lock();
+        write_annotations(suites, &mut stdout)?;
+        stdout.flush()?;
+        Ok(())
+    }
+}
+
+/// Single `::error` workflow command.
+#[derive(Debug, Eq, PartialEq)]
+pub struct Annotation {
+    pub file: Option<String>,
+    pub line: Option<u32>,
+    pub title: String,
+    pub message: String,
+}
+
+impl Annotation {
+    /// Annotation for a failed or erred out test, `None` for the rest.
+    pub fn from_test_case(suite: &str, case: &TestCase) -> Option<Annotation> {
+        let title = format!("{} {}", suite, case.name);
+        if let Some(ref failure) = case.failure {
+            let annotation = match find_panic(&failure.message) {
+                Some(panic) => Annotation {
+                    file: Some(panic.file),
+                    line: Some(panic.line),
+                    title,
+                    message: panic.message,
+                },
+                None => Annotation {
+                    file: None,
+                    line: None,
+                    title,
+                    message: failure.message.trim_end().to_string(),
+                },
+            };
+            Some(annotation)
+        } else {
+            case.error.as_ref().map(|error| Annotation {
+                file: None,
+                line: None,
+                title,
+                message: error.message.clone(),
+            })
+        }
+    }
+}
+
+impl fmt::Display for Annotation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "::error ")?;
+        if let Some(ref file) = self.file {
+            write!(f, "file={},", escape_property(file))?;
+        }
+        if let Some(line) = self.line {
+            write!(f, "line={},", line)?;
+        }
+        write!(f, "title={}::{}", escape_property(&self.title), escape_data(&self.message))
+    }
+}
+
+pub fn write_annotations<W: Write>(suites: &[TestSuite], mut writer: W) -> io::Result<()> {
+    for suite in suites {
+        for case in &suite.test_cases {
+            if let Some(annotation) = Annotation::from_test_case(&suite.name, case) {
+                writeln!(writer, "{}", annotation)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn escape_data(data: &str) -> String {
+    data.replace('%', "%25").replace('\r', "%0D").replace('\n', "%0A")
+}
+
+fn escape_property(property: &str) -> String {
+    escape_data(property).replace(':', "%3A").replace(',', "%2C")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{write_annotations, Annotation};
+    use crate::junit::TestSuite;
+    use crate::results::parse_test_results;
+
+    #[test]
+    fn escaped_commands() {
+        let annotation = Annotation {
+            file: Some(String::from("tests/boom.rs")),
+            line: Some(12),
+            title: String::from("[default] boom: a, b"),
+            message: String::from("100% broken\nreally"),
+        };
+
+        assert_eq!(
+            "::error file=tests/boom.rs,line=12,title=[default] boom%3A a%2C b::100%25 broken%0Areally",
+            annotation.to_string()
+        );
+    }
+
+    #[test]
+    fn failures_and_errors() {
+        let stdout = r#"{ "type": "suite", "event": "started", "test_count": 3 }
+{ "type": "test", "event": "started", "name": "works" }
+{ "type": "test", "name": "works", "event": "ok" }
+{ "type": "test", "event": "started", "name": "broken" }
+{ "type": "test", "name": "broken", "event": "failed", "stdout": "thread 'broken' panicked at 'boom', tests/boom.rs:7:5\n" }
+{ "type": "test", "event": "started", "name": "unwraps" }
+{ "type": "test", "name": "unwraps", "event": "failed", "stdout": "Error: NotFound\n" }"#;
+        let suite = TestSuite::with_abort(
+            parse_test_results(stdout).unwrap(),
+            String::from("[default] boom"),
+            None,
+        )
+        .unwrap();
+
+        let mut output = Vec::new();
+        write_annotations(&[suite], &mut output).unwrap();
+
+        let expected = "::error file=tests/boom.rs,line=7,title=[default] boom broken::boom\n::error title=[default] boom unwraps::Error: NotFound\n";
+        assert_eq!(expected, String::from_utf8(output).unwrap());
+    }
+}
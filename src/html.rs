@@ -0,0 +1,262 @@
+//! Support for export as a self-contained HTML page.
+//!
+//! One page covers every workflow of a run. Styles and scripts are inlined, so the page can be
+//! opened straight from disk or archived as a single CI artifact.
+
+use crate::configuration::RunSettings;
+use crate::errors::SuityError;
+use crate::junit::{TestCase, TestSuite};
+use crate::report::{create_file, RunReporter, WorkflowResults};
+use std::io::{self, Write};
+use std::path::Path;
+
+/// Name of the page within output directory of a run.
+pub const FILE_NAME: &str = "report.html";
+
+/// Writes HTML report of a whole run.
+pub struct HtmlReporter;
+
+impl RunReporter for HtmlReporter {
+    fn report_run(&self, output: &Path, _settings: &RunSettings, workflows: &[WorkflowResults]) -> Result<(), SuityError> {
+        let mut file = create_file(&output.join(FILE_NAME))?;
+        write_as_html(workflows, &mut file)?;
+        file.flush()?;
+        Ok(())
+    }
+}
+
+const STYLE: &str = r#"
+body { font-family: sans-serif; margin: 2em; color: #222; }
+table { border-collapse: collapse; width: 100%; margin-bottom: 1.5em; }
+caption { text-align: left; font-weight: bold; padding: 0.3em 0; }
+th, td { text-align: left; padding: 0.2em 0.6em; border-bottom: 1px solid #ddd; vertical-align: top; }
+th.duration { cursor: pointer; white-space: nowrap; }
+td.duration { white-space: nowrap; }
+pre { background: #f6f6f6; padding: 0.5em; overflow-x: auto; }
+.passed { color: #22863a; }
+.failed, .error, .build-failed { color: #cb2431; }
+.skipped { color: #b08800; }
+"#;
+
+const SCRIPT: &str = r#"
+document.getElementById('filter').addEventListener('change', function () {
+  var status = this.value;
+  document.querySelectorAll('tr.case').forEach(function (row) {
+    row.hidden = status !== 'all' && row.dataset.status !== status;
+  });
+});
+document.querySelectorAll('th.duration').forEach(function (th) {
+  th.addEventListener('click', function () {
+    var body = th.closest('table').tBodies[0];
+    var descending = th.dataset.order !== 'desc';
+    th.dataset.order = descending ? 'desc' : 'asc';
+    Array.from(body.rows).sort(function (a, b) {
+      var delta = parseFloat(a.dataset.duration || '-1') - parseFloat(b.dataset.duration || '-1');
+      return descending ? -delta : delta;
+    }).forEach(function (row) { body.appendChild(row); });
+  });
+});
+"#;
+
+pub fn write_as_html<W: Write>(workflows: &[WorkflowResults], mut writer: W) -> io::Result<()> {
+    let suites = || workflows.iter().flat_map(|w| w.suites.unwrap_or(&[]));
+    let tests: u64 = suites().map(|s| s.tests).sum();
+    let failed: u64 = suites().map(|s| s.failures + s.errors).sum();
+    let skipped: u64 = suites().map(|s| s.skipped).sum();
+    let build_failed = workflows.iter().filter(|w| w.suites.is_none()).count();
+
+    writeln!(writer, "<!DOCTYPE html>")?;
+    writeln!(writer, "<html lang=\"en\">")?;
+    writeln!(writer, "<head>")?;
+    writeln!(writer, "<meta charset=\"utf-8\">")?;
+    writeln!(writer, "<title>Test results</title>")?;
+    writeln!(writer, "<style>{}</style>", STYLE)?;
+    writeln!(writer, "</head>")?;
+    writeln!(writer, "<body>")?;
+    writeln!(writer, "<h1>Test results</h1>")?;
+    write!(
+        writer,
+        "<p class=\"summary\">{} tests, <span class=\"passed\">{} passed</span>, <span class=\"failed\">{} failed</span>, <span class=\"skipped\">{} skipped</span>",
+        tests,
+        tests.saturating_sub(failed + skipped),
+        failed,
+        skipped
+    )?;
+    if build_failed > 0 {
+        write!(writer, ", <span class=\"build-failed\">{} workflows didn't compile</span>", build_failed)?;
+    }
+    writeln!(writer, "</p>")?;
+    writeln!(writer, "<p><label>Show <select id=\"filter\">")?;
+    for status in &["all", "passed", "failed", "error", "skipped"] {
+        writeln!(writer, "<option value=\"{0}\">{0}</option>", status)?;
+    }
+    writeln!(writer, "</select></label></p>")?;
+
+    for workflow in workflows {
+        write_workflow(&mut writer, workflow)?;
+    }
+
+    writeln!(writer, "<script>{}</script>", SCRIPT)?;
+    writeln!(writer, "</body>")?;
+    writeln!(writer, "</html>")
+}
+
+fn write_workflow<W: Write>(writer: &mut W, workflow: &WorkflowResults) -> io::Result<()> {
+    let runspec = workflow.runspec;
+    writeln!(writer, "<section class=\"workflow\">")?;
+    let suites = match workflow.suites {
+        Some(suites) => suites,
+        None => {
+            writeln!(writer, "<h2>{} <span class=\"build-failed\">didn't compile</span></h2>", escape(&runspec.name))?;
+            return writeln!(writer, "</section>");
+        }
+    };
+    let status = passed_or_failed(suites.iter().map(|s| s.failures + s.errors).sum());
+    writeln!(
+        writer,
+        "<h2>{} <span class=\"{1}\">{1}</span></h2>",
+        escape(&runspec.name),
+        status
+    )?;
+    if !runspec.features.is_empty() {
+        writeln!(writer, "<p>Features: {}</p>", escape(&runspec.features.join(", ")))?;
+    }
+    for suite in suites {
+        write_suite(writer, suite)?;
+    }
+    writeln!(writer, "</section>")
+}
+
+fn write_suite<W: Write>(writer: &mut W, suite: &TestSuite) -> io::Result<()> {
+    let status = passed_or_failed(suite.failures + suite.errors);
+    writeln!(writer, "<table class=\"suite\">")?;
+    write!(
+        writer,
+        "<caption>{} <span class=\"{1}\">{1}</span>, {2} tests, {3} failed, {4} skipped",
+        escape(&suite.name),
+        status,
+        suite.tests,
+        suite.failures + suite.errors,
+        suite.skipped
+    )?;
+    if let Some(time) = suite.time {
+        write!(writer, ", {:.3}s", time.as_secs_f64())?;
+    }
+    writeln!(writer, "</caption>")?;
+    writeln!(writer, "<thead><tr><th>Status</th><th>Test</th><th class=\"duration\">Duration</th></tr></thead>")?;
+    writeln!(writer, "<tbody>")?;
+    for case in &suite.test_cases {
+        write_test_case(writer, case)?;
+    }
+    writeln!(writer, "</tbody>")?;
+    if let Some(ref output) = suite.system_out {
+        writeln!(
+            writer,
+            "<tfoot><tr><td colspan=\"3\"><details><summary>Output</summary><pre>{}</pre></details></td></tr></tfoot>",
+            escape(output)
+        )?;
+    }
+    writeln!(writer, "</table>")
+}
+
+fn write_test_case<W: Write>(writer: &mut W, case: &TestCase) -> io::Result<()> {
+    let (status, details) = if let Some(ref failure) = case.failure {
+        ("failed", Some(failure.message.clone()))
+    } else if let Some(ref error) = case.error {
+        let details = match error.output {
+            Some(ref output) => format!("{}\n\n{}", error.message, output),
+            None => error.message.clone(),
+        };
+        ("error", Some(details))
+    } else if let Some(ref skipped) = case.skipped {
+        ("skipped", skipped.message.clone())
+    } else {
+        ("passed", None)
+    };
+
+    write!(writer, "<tr class=\"case\" data-status=\"{}\"", status)?;
+    if let Some(time) = case.time {
+        write!(writer, " data-duration=\"{}\"", time.as_secs_f64())?;
+    }
+    write!(writer, "><td class=\"{0}\">{0}</td><td>", status)?;
+    match details {
+        Some(ref details) if status == "skipped" => {
+            write!(writer, "{} <span class=\"skipped\">({})</span>", escape(&case.name), escape(details))?
+        }
+        Some(ref details) => write!(
+            writer,
+            "<details><summary>{}</summary><pre>{}</pre></details>",
+            escape(&case.name),
+            escape(details)
+        )?,
+        None => write!(writer, "{}", escape(&case.name))?,
+    }
+    write!(writer, "</td><td class=\"duration\">")?;
+    if let Some(time) = case.time {
+        write!(writer, "{:.3}s", time.as_secs_f64())?;
+    }
+    writeln!(writer, "</td></tr>")
+}
+
+fn passed_or_failed(failed: u64) -> &'static str {
+    if failed == 0 {
+        "passed"
+    } else {
+        "failed"
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::write_as_html;
+    use crate::junit::TestSuite;
+    use crate::report::WorkflowResults;
+    use crate::results::parse_test_results;
+    use crate::runspec::Runspec;
+
+    #[test]
+    fn run_with_broken_workflow() {
+        let stdout = r#"{ "type": "suite", "event": "started", "test_count": 2 }
+{ "type": "test", "event": "started", "name": "works" }
+{ "type": "test", "name": "works", "event": "ok", "exec_time": 0.5 }
+{ "type": "test", "event": "started", "name": "broken" }
+{ "type": "test", "name": "broken", "event": "failed", "stdout": "left: <1>" }
+{ "type": "suite", "event": "failed", "passed": 1, "failed": 1, "allowed_fail": 0, "ignored": 0, "measured": 0, "filtered_out": 0 }"#;
+        let suites = vec![TestSuite::new(parse_test_results(stdout).unwrap(), String::from("[default] Lib-tests")).unwrap()];
+        let default = Runspec::default();
+        let serde = Runspec {
+            name: String::from("serde"),
+            ..Runspec::default()
+        };
+        let workflows = [
+            WorkflowResults { runspec: &default, suites: Some(&suites) },
+            WorkflowResults { runspec: &serde, suites: None },
+        ];
+
+        let mut output = Vec::new();
+        write_as_html(&workflows, &mut output).unwrap();
+        let html = String::from_utf8(output).unwrap();
+
+        assert!(html.contains("2 tests, <span class=\"passed\">1 passed</span>, <span class=\"failed\">1 failed</span>"));
+        assert!(html.contains("1 workflows didn't compile"));
+        assert!(html.contains(r#"<tr class="case" data-status="passed" data-duration="0.5"><td class="passed">passed</td><td>works</td><td class="duration">0.500s</td></tr>"#));
+        assert!(html.contains("<details><summary>broken</summary><pre>left: &lt;1&gt;</pre></details>"));
+        assert!(html.contains("<h2>serde <span class=\"build-failed\">didn't compile</span></h2>"));
+        assert!(!html.contains("<link") && !html.contains(" src="));
+    }
+}
@@ -0,0 +1,55 @@
+//! Stable identifiers for reports that need GUIDs.
+//!
+//! Ids are derived from names with 128-bit FNV-1a, so the same test gets the same id on every
+//! run, machine and toolchain. `std`'s `DefaultHasher` gives no such guarantee.
+
+const FNV_OFFSET_BASIS: u128 = 0x6c62_272e_07bb_0142_62b8_2175_6295_c58d;
+const FNV_PRIME: u128 = 0x0000_0000_0100_0000_0000_0000_0000_013b;
+/// Can't appear in UTF-8, so `["ab", "c"]` and `["a", "bc"]` hash differently.
+const SEPARATOR: u8 = 0xff;
+
+fn fnv1a(parts: &[&str]) -> u128 {
+    let mut hash = FNV_OFFSET_BASIS;
+    for part in parts {
+        for byte in part.bytes().chain(Some(SEPARATOR)) {
+            hash ^= u128::from(byte);
+            hash = hash.wrapping_mul(FNV_PRIME);
+        }
+    }
+    hash
+}
+
+/// Name-based GUID, formatted as a version 8 (custom) UUID.
+pub(crate) fn guid(parts: &[&str]) -> String {
+    let hash = fnv1a(parts);
+    let hash = hash & !(0xf << 76) | (0x8 << 76);
+    let hash = hash & !(0x3 << 62) | (0x2 << 62);
+    format!(
+        "{:08x}-{:04x}-{:04x}-{:04x}-{:012x}",
+        hash >> 96,
+        (hash >> 80) & 0xffff,
+        (hash >> 64) & 0xffff,
+        (hash >> 48) & 0xffff,
+        hash & 0xffff_ffff_ffff
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fnv1a, guid};
+
+    #[test]
+    fn fnv1a_of_nothing_is_offset_basis() {
+        assert_eq!(0x6c62_272e_07bb_0142_62b8_2175_6295_c58d, fnv1a(&[]));
+    }
+
+    #[test]
+    fn stable_guids() {
+        let id = guid(&["default", "works"]);
+        assert_eq!("a1fa896c-ac98-89f4-9daa-2bf787037890", id);
+        assert_eq!(id, guid(&["default", "works"]));
+        assert_ne!(id, guid(&["default", "broken"]));
+        assert_ne!(guid(&["ab", "c"]), guid(&["a", "bc"]));
+        assert_eq!(b'8', id.as_bytes()[14]);
+    }
+}
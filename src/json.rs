@@ -0,0 +1,211 @@
+//! Support for export in suity's own JSON format.
+//!
+//! Document has the following shape, `SCHEMA_VERSION` is bumped on every incompatible change:
+//!
+//! ```json
+//! {
+//!   "schema_version": 1,
+//!   "workflows": [{
+//!     "name": "default",
+//!     "features": ["serde"],
+//!     "status": "failed",
+//!     "suites": [{
+//!       "name": "[default] Lib-tests",
+//!       "status": "failed",
+//!       "tests": 2, "failures": 1, "errors": 0, "skipped": 0,
+//!       "duration": 0.51,
+//!       "output": null,
+//!       "cases": [{
+//!         "name": "tests::works",
+//!         "status": "failed",
+//!         "duration": 0.25,
+//!         "message": "assertion failed",
+//!         "output": null
+//!       }]
+//!     }]
+//!   }]
+//! }
+//! ```
+//!
+//! `status` is `passed` or `failed` for workflows and suites, and `passed`, `failed`, `error` or
+//! `skipped` for cases. Durations are in seconds and `null` when unknown.
+
+use crate::junit::{TestCase, TestSuite};
+use crate::report::FileReporter;
+use crate::runspec::Runspec;
+use std::io::{self, Write};
+
+/// Version of the document, see module documentation.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Writes JSON report.
+pub struct JsonReporter;
+
+impl FileReporter for JsonReporter {
+    fn extension(&self) -> &'static str {
+        "json"
+    }
+
+    fn write(&self, runspec: &Runspec, suites: &[TestSuite], output: &mut dyn Write) -> io::Result<()> {
+        write_as_json(runspec, suites, output)
+    }
+}
+
+#[derive(Serialize)]
+struct Document<'a> {
+    schema_version: u32,
+    workflows: Vec<Workflow<'a>>,
+}
+
+#[derive(Serialize)]
+struct Workflow<'a> {
+    name: &'a str,
+    features: &'a [String],
+    status: &'static str,
+    suites: Vec<Suite<'a>>,
+}
+
+#[derive(Serialize)]
+struct Suite<'a> {
+    name: &'a str,
+    status: &'static str,
+    tests: u64,
+    failures: u64,
+    errors: u64,
+    skipped: u64,
+    duration: Option<f64>,
+    output: Option<&'a str>,
+    cases: Vec<Case<'a>>,
+}
+
+#[derive(Serialize)]
+struct Case<'a> {
+    name: &'a str,
+    status: &'static str,
+    duration: Option<f64>,
+    message: Option<&'a str>,
+    output: Option<&'a str>,
+}
+
+impl<'a> From<&'a TestSuite> for Suite<'a> {
+    fn from(suite: &'a TestSuite) -> Suite<'a> {
+        Suite {
+            name: &suite.name,
+            status: passed_or_failed(suite.failures + suite.errors),
+            tests: suite.tests,
+            failures: suite.failures,
+            errors: suite.errors,
+            skipped: suite.skipped,
+            duration: suite.time.map(|time| time.as_secs_f64()),
+            output: suite.system_out.as_deref(),
+            cases: suite.test_cases.iter().map(Case::from).collect(),
+        }
+    }
+}
+
+impl<'a> From<&'a TestCase> for Case<'a> {
+    fn from(case: &'a TestCase) -> Case<'a> {
+        let (status, message, output) = if let Some(ref failure) = case.failure {
+            ("failed", Some(failure.message.as_str()), case.system_out.as_deref())
+        } else if let Some(ref error) = case.error {
+            ("error", Some(error.message.as_str()), error.output.as_deref())
+        } else if let Some(ref skipped) = case.skipped {
+            ("skipped", skipped.message.as_deref(), case.system_out.as_deref())
+        } else {
+            ("passed", None, case.system_out.as_deref())
+        };
+        Case {
+            name: &case.name,
+            status,
+            duration: case.time.map(|time| time.as_secs_f64()),
+            message,
+            output,
+        }
+    }
+}
+
+fn passed_or_failed(failed: u64) -> &'static str {
+    if failed == 0 {
+        "passed"
+    } else {
+        "failed"
+    }
+}
+
+pub fn write_as_json<W: Write>(runspec: &Runspec, suites: &[TestSuite], writer: W) -> io::Result<()> {
+    let document = Document {
+        schema_version: SCHEMA_VERSION,
+        workflows: vec![Workflow {
+            name: &runspec.name,
+            features: &runspec.features,
+            status: passed_or_failed(suites.iter().map(|s| s.failures + s.errors).sum()),
+            suites: suites.iter().map(Suite::from).collect(),
+        }],
+    };
+    serde_json::to_writer_pretty(writer, &document).map_err(io::Error::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::write_as_json;
+    use crate::junit::{crashed_suite, TestSuite};
+    use crate::results::parse_test_results;
+    use crate::runspec::Runspec;
+    use serde_json::{json, Value};
+
+    #[test]
+    fn workflow_document() {
+        let stdout = r#"{ "type": "suite", "event": "started", "test_count": 3 }
+{ "type": "test", "event": "started", "name": "works" }
+{ "type": "test", "name": "works", "event": "ok", "exec_time": 0.5 }
+{ "type": "test", "event": "started", "name": "broken" }
+{ "type": "test", "name": "broken", "event": "failed", "stdout": "assertion failed" }
+{ "type": "test", "name": "needs_zfs", "event": "ignored", "message": "requires root" }
+{ "type": "suite", "event": "failed", "passed": 1, "failed": 1, "allowed_fail": 0, "ignored": 1, "measured": 0, "filtered_out": 0, "exec_time": 1.0 }"#;
+        let suite = TestSuite::new(parse_test_results(stdout).unwrap(), String::from("[serde] Lib-tests")).unwrap();
+        let runspec = Runspec {
+            name: String::from("serde"),
+            features: vec![String::from("serde")],
+            ..Runspec::default()
+        };
+
+        let mut output = Vec::new();
+        write_as_json(&runspec, &[suite], &mut output).unwrap();
+        let document: Value = serde_json::from_slice(&output).unwrap();
+
+        let expected = json!({
+            "schema_version": 1,
+            "workflows": [{
+                "name": "serde",
+                "features": ["serde"],
+                "status": "failed",
+                "suites": [{
+                    "name": "[serde] Lib-tests",
+                    "status": "failed",
+                    "tests": 3, "failures": 1, "errors": 0, "skipped": 1,
+                    "duration": 1.0,
+                    "output": null,
+                    "cases": [
+                        { "name": "works", "status": "passed", "duration": 0.5, "message": null, "output": null },
+                        { "name": "broken", "status": "failed", "duration": null, "message": "assertion failed", "output": null },
+                        { "name": "needs_zfs", "status": "skipped", "duration": null, "message": "requires root", "output": null }
+                    ]
+                }]
+            }]
+        });
+        assert_eq!(expected, document);
+    }
+
+    #[test]
+    fn crashed_case_is_an_error() {
+        let suite = crashed_suite();
+
+        let mut output = Vec::new();
+        write_as_json(&Runspec::default(), &[suite], &mut output).unwrap();
+        let document: Value = serde_json::from_slice(&output).unwrap();
+
+        let case = &document["workflows"][0]["suites"][0]["cases"][3];
+        assert_eq!("error", case["status"]);
+        assert_eq!("oops", case["output"]);
+    }
+}
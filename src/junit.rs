@@ -1,8 +1,11 @@
-/// Support for export in JUnit format.
+//! Support for export in JUnit format.
 
 use crate::results::{Event, EventKind};
 use crate::errors::SuityError;
+use crate::report::FileReporter;
+use crate::runspec::Runspec;
 use std::io::{Write,self};
+use std::time::{Duration, SystemTime};
 use xml_writer::XmlWriter;
 
 /// Indicates that the test failed. A failure is a test which the code has explicitly failed by
@@ -14,6 +17,35 @@ pub struct Failure {
     pub message: String,
 }
 
+/// Indicates that the test was interrupted before it could pass or fail, e.g. test binary crashed
+/// or was killed.
+#[derive(Debug, Eq, PartialEq)]
+pub struct Error {
+    /// Why test didn't finish
+    pub message: String,
+    /// Relevant output of test process, e.g. its stderr
+    pub output: Option<String>,
+}
+
+/// How test process terminated when it didn't finish on its own terms.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Abort {
+    /// Human readable reason, e.g. "terminated by signal 11 (SIGSEGV)"
+    pub reason: String,
+    /// Captured stderr of the process
+    pub stderr: String,
+    /// Test that brought the process down, e.g. by running out of time. Other unfinished tests
+    /// are reported as interrupted by it. `None` if no single test is to blame.
+    pub culprit: Option<String>,
+}
+
+/// Indicates that the test was not run, i.e. marked with `#[ignore]`.
+#[derive(Debug, Eq, PartialEq)]
+pub struct Skipped {
+    /// Reason why test was skipped, if any
+    pub message: Option<String>,
+}
+
 /// Contains result of a test case
 #[derive(Debug, Eq, PartialEq)]
 pub struct TestCase {
@@ -21,19 +53,35 @@ pub struct TestCase {
     pub name: String,
     /// Indicates that test failed
     pub failure: Option<Failure>,
+    /// Indicates that test erred out
+    pub error: Option<Error>,
+    /// Indicates that test was skipped
+    pub skipped: Option<Skipped>,
+    /// Output captured while test was running
+    pub system_out: Option<String>,
+    /// How long test took to run
+    pub time: Option<Duration>,
 }
 
 #[derive(Debug, Eq, PartialEq)]
 pub struct TestSuite {
     /// Name of the test suite
     pub name: String,
-    /// How many tests erred out. In rust we can't can't detect it :(
+    /// How many tests erred out, i.e. started but never finished.
     pub errors: u64,
     /// How many tests failed.
     pub failures: u64,
+    /// How many tests were skipped.
+    pub skipped: u64,
     /// Total amount of tests
     pub tests: u64,
     pub test_cases: Vec<TestCase>,
+    /// Output that doesn't belong to any specific test
+    pub system_out: Option<String>,
+    /// How long the whole suite took to run
+    pub time: Option<Duration>,
+    /// When test process of the suite was started
+    pub timestamp: Option<SystemTime>,
 }
 
 
@@ -43,68 +91,241 @@ impl TestSuite {
     /// NOTE: Only works if event stream is related to a single testsuite. You have to run unit, doc
     /// and integration tests separately!
     pub fn new(events: Vec<Event>, name: String) -> Result<TestSuite,SuityError> {
+        TestSuite::with_abort(events, name, None)
+    }
 
-        let mut suite = TestSuite {
-            name,
-            errors: 0,
-            failures:0,
-            tests: 0,
-            test_cases: Vec::new()
+    /// Create TestSuite from event stream of a process that might have been terminated
+    /// prematurely. If event stream was cut short, tests that started but never finished are
+    /// reported as errors carrying `abort`'s reason and output.
+    pub fn with_abort(events: Vec<Event>, name: String, abort: Option<Abort>) -> Result<TestSuite,SuityError> {
+        let mut builder = TestSuiteBuilder::new(name);
+        for event in events {
+            builder.push(event)?;
+        }
+        Ok(builder.finish(abort))
+    }
+
+    /// When the suite started and stopped, `None` if start time is unknown. Killed suites don't
+    /// report their duration, their tests' durations are used instead.
+    pub fn span(&self) -> Option<(SystemTime, SystemTime)> {
+        let duration = self
+            .time
+            .unwrap_or_else(|| self.test_cases.iter().filter_map(|case| case.time).sum());
+        self.timestamp.map(|start| (start, start + duration))
+    }
+
+    fn add_errors(&mut self, running: Vec<String>, abort: Option<Abort>) {
+        // Process died outside of any test, still has to show up somewhere.
+        let names = if running.is_empty() && abort.is_some() {
+            vec![self.name.clone()]
+        } else {
+            running
+        };
+        let (reason, output, culprit) = match abort {
+            Some(abort) => (abort.reason, Some(abort.stderr).filter(|s| !s.is_empty()), abort.culprit),
+            None => (String::from("test did not finish"), None, None),
         };
+        for name in names {
+            let message = match culprit {
+                Some(ref culprit) if culprit != &name => format!("interrupted: {}", reason),
+                _ => reason.clone(),
+            };
+            self.errors += 1;
+            self.test_cases.push(TestCase {
+                name,
+                failure: None,
+                error: Some(Error {
+                    message,
+                    output: output.clone(),
+                }),
+                skipped: None,
+                system_out: None,
+                time: None,
+            });
+        }
+        // Tests that never started have no outcome to report, count only the recorded ones so
+        // `tests` matches test cases.
+        self.tests = self.test_cases.len() as u64;
+    }
+}
 
-        let mut counter = 0;
+/// Output of lib tests killed by SIGSEGV: `works` passed, `broken` failed, `needs_zfs` was ignored
+/// and `segfaults` never finished.
+#[cfg(test)]
+pub(crate) const CRASHED_SUITE_OUTPUT: &str = r#"{ "type": "suite", "event": "started", "test_count": 4 }
+{ "type": "test", "event": "started", "name": "works" }
+{ "type": "test", "name": "works", "event": "ok", "exec_time": 0.25 }
+{ "type": "test", "event": "started", "name": "broken" }
+{ "type": "test", "name": "broken", "event": "failed", "stdout": "thread 'broken' panicked at 'boom', src/lib.rs:7:5\n", "exec_time": 0.5 }
+{ "type": "test", "name": "needs_zfs", "event": "ignored", "message": "requires root" }
+{ "type": "test", "event": "started", "name": "segfaults" }"#;
 
+/// Suite of `CRASHED_SUITE_OUTPUT` with "oops" on stderr, started at a fixed time. Shared by tests
+/// of every report format.
+#[cfg(test)]
+pub(crate) fn crashed_suite() -> TestSuite {
+    let abort = Abort {
+        reason: String::from("terminated by signal 11 (SIGSEGV)"),
+        stderr: String::from("oops"),
+        culprit: None,
+    };
+    let events = crate::results::parse_test_results(CRASHED_SUITE_OUTPUT).unwrap();
+    let mut suite = TestSuite::with_abort(events, String::from("[default] Lib-tests"), Some(abort)).unwrap();
+    suite.timestamp = Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1_600_000_000));
+    suite
+}
 
-        for event in events {
-            match event {
-                Event::Suite(s) => {
-                    match s.event {
-                        EventKind::Ignored => { /* no-op */ },
-                        EventKind::Started => {
-                            suite.tests = s.test_count.unwrap();
-                            counter += 1;
-                            if counter > 1 {
-                                return Err(SuityError::MultipleTestRuns);
-                            }
-                        },
-                        EventKind::Failed | EventKind::Ok => {
-                            suite.failures = s.failed.unwrap();
+/// Builds TestSuite incrementally from events as they arrive.
+#[derive(Debug)]
+pub struct TestSuiteBuilder {
+    suite: TestSuite,
+    counter: u64,
+    finished: bool,
+    running: Vec<String>,
+}
+
+impl TestSuiteBuilder {
+    pub fn new(name: String) -> TestSuiteBuilder {
+        TestSuiteBuilder {
+            suite: TestSuite {
+                name,
+                errors: 0,
+                failures:0,
+                skipped: 0,
+                tests: 0,
+                test_cases: Vec::new(),
+                system_out: None,
+                time: None,
+                timestamp: None,
+            },
+            counter: 0,
+            finished: false,
+            running: Vec::new(),
+        }
+    }
+
+    /// Name of the suite being built.
+    pub fn name(&self) -> &str {
+        &self.suite.name
+    }
+
+    /// Apply next event. Returns test case if event completed one.
+    pub fn push(&mut self, event: Event) -> Result<Option<&TestCase>, SuityError> {
+        let suite = &mut self.suite;
+        match event {
+            Event::Suite(s) => {
+                match s.event {
+                    EventKind::Ignored | EventKind::Timeout => { /* no-op */ },
+                    EventKind::Started => {
+                        suite.tests = s.test_count.unwrap_or(0);
+                        self.counter += 1;
+                        if self.counter > 1 {
+                            return Err(SuityError::MultipleTestRuns);
                         }
+                    },
+                    EventKind::Failed | EventKind::Ok => {
+                        self.finished = true;
+                        suite.failures = s.failed.unwrap_or(0);
+                        suite.system_out = s.stdout;
+                        suite.time = s.exec_time.and_then(to_duration);
                     }
-                },
-                Event::Test(t) => {
-                    match t.event {
-                        EventKind::Started => { /* no-op */ },
-                        EventKind::Ignored => { /* no-op */ },
-                        EventKind::Ok => {
-                            suite.test_cases.push(
-                                TestCase {
-                                    name: t.name,
-                                    failure: None
-                                }
-                            )
+                }
+                Ok(None)
+            },
+            Event::Test(t) => {
+                let test_case = match t.event {
+                    EventKind::Started => {
+                        self.running.retain(|name| name != &t.name);
+                        self.running.push(t.name);
+                        return Ok(None);
+                    },
+                    // libtest only warns about slow tests, they are still running.
+                    EventKind::Timeout => return Ok(None),
+                    EventKind::Ignored => {
+                        suite.skipped += 1;
+                        TestCase {
+                            name: t.name,
+                            failure: None,
+                            error: None,
+                            skipped: Some(Skipped {
+                                message: t.message,
+                            }),
+                            system_out: None,
+                            time: None,
                         }
-                        EventKind::Failed => {
-                            suite.test_cases.push(
-                                TestCase {
-                                    name: t.name,
-                                    failure: Some(Failure{
-                                        message: t.stdout.unwrap()
-                                    })
-                                }
-                            )
+                    },
+                    EventKind::Ok => {
+                        TestCase {
+                            name: t.name,
+                            failure: None,
+                            error: None,
+                            skipped: None,
+                            system_out: t.stdout,
+                            time: t.exec_time.and_then(to_duration),
                         }
                     }
-
-                }
+                    EventKind::Failed => {
+                        suite.failures += 1;
+                        TestCase {
+                            name: t.name,
+                            failure: Some(Failure{
+                                message: t.stdout.unwrap_or_default()
+                            }),
+                            error: None,
+                            skipped: None,
+                            system_out: None,
+                            time: t.exec_time.and_then(to_duration),
+                        }
+                    }
+                };
+                self.running.retain(|name| name != &test_case.name);
+                suite.test_cases.push(test_case);
+                Ok(suite.test_cases.last())
             }
         }
-        Ok(suite)
     }
+
+    /// Attach output that doesn't belong to any test.
+    pub fn append_system_out(&mut self, output: String) {
+        match self.suite.system_out {
+            Some(ref mut system_out) => system_out.push_str(&output),
+            None => self.suite.system_out = Some(output),
+        }
+    }
+
+    /// Finish building. If event stream was cut short, tests that started but never finished are
+    /// reported as errors carrying `abort`'s reason and output.
+    pub fn finish(mut self, abort: Option<Abort>) -> TestSuite {
+        if !self.finished {
+            self.suite.add_errors(self.running, abort);
+        }
+        self.suite
+    }
+}
+
+fn to_duration(seconds: f64) -> Option<Duration> {
+    Duration::try_from_secs_f64(seconds).ok()
+}
+
+fn format_time(time: &Duration) -> String {
+    format!("{:.3}", time.as_secs_f64())
 }
 
 
-pub fn write_as_xml<W: Write>(suites: &Vec<TestSuite>, writer: W) -> Result<(),io::Error> {
+/// Writes JUnit XML report.
+pub struct JUnitReporter;
+
+impl FileReporter for JUnitReporter {
+    fn extension(&self) -> &'static str {
+        "xml"
+    }
+
+    fn write(&self, _runspec: &Runspec, suites: &[TestSuite], output: &mut dyn Write) -> io::Result<()> {
+        write_as_xml(suites, output)
+    }
+}
+
+pub fn write_as_xml<W: Write>(suites: &[TestSuite], writer: W) -> Result<(),io::Error> {
     let mut xml = XmlWriter::new(writer);
     xml.dtd("utf-8")?;
     xml.begin_elem("testsuites")?;
@@ -113,17 +334,45 @@ pub fn write_as_xml<W: Write>(suites: &Vec<TestSuite>, writer: W) -> Result<(),i
         xml.attr_esc("name", &suite.name)?;
         xml.attr("errors", suite.errors.to_string().as_str())?;
         xml.attr("failures", suite.failures.to_string().as_str())?;
+        xml.attr("skipped", suite.skipped.to_string().as_str())?;
         xml.attr("tests", suite.tests.to_string().as_str())?;
+        if let Some(ref time) = suite.time {
+            xml.attr("time", &format_time(time))?;
+        }
         for testcase in &suite.test_cases {
             xml.begin_elem("testcase")?;
             xml.attr("name", &testcase.name)?;
+            if let Some(ref time) = testcase.time {
+                xml.attr("time", &format_time(time))?;
+            }
             if let Some(ref failure) = &testcase.failure {
                 xml.begin_elem("failure")?;
                 xml.attr_esc("message", &failure.message)?;
                 xml.end_elem()?;
             }
+            if let Some(ref error) = &testcase.error {
+                xml.begin_elem("error")?;
+                xml.attr_esc("message", &error.message)?;
+                if let Some(ref output) = error.output {
+                    xml.text(output)?;
+                }
+                xml.end_elem()?;
+            }
+            if let Some(ref skipped) = &testcase.skipped {
+                xml.begin_elem("skipped")?;
+                if let Some(ref message) = skipped.message {
+                    xml.attr_esc("message", message)?;
+                }
+                xml.end_elem()?;
+            }
+            if let Some(ref system_out) = testcase.system_out {
+                xml.elem_text("system-out", system_out)?;
+            }
             xml.end_elem()?;
         }
+        if let Some(ref system_out) = suite.system_out {
+            xml.elem_text("system-out", system_out)?;
+        }
         xml.end_elem()?;
     }
     xml.end_elem()?;
@@ -135,7 +384,9 @@ pub fn write_as_xml<W: Write>(suites: &Vec<TestSuite>, writer: W) -> Result<(),i
 mod tests {
 
     use crate::results::parse_test_results;
-    use super::{TestSuite, TestCase, Failure, write_as_xml};
+    use crate::results::{Event, EventKind};
+    use super::{Abort, Error, TestSuite, TestSuiteBuilder, TestCase, Failure, Skipped, crashed_suite, write_as_xml};
+    use std::time::Duration;
 
     #[test]
     fn test_simple_output() {
@@ -144,20 +395,28 @@ mod tests {
 { "type": "test", "name": "parsers::test::test_zpools_on_single_zpool", "event": "ok" }
 { "type": "suite", "event": "ok", "passed": 1, "failed": 0, "allowed_fail": 0, "ignored": 0, "measured": 0, "filtered_out": 40 }"#;
 
-        let events = parse_test_results(stdout);
+        let events = parse_test_results(stdout).unwrap();
 
         let name = String::from("Doc Tests");
         let test_name = String::from("parsers::test::test_zpools_on_single_zpool");
         let expected_test_case = TestCase {
             name: test_name.clone(),
             failure: None,
+            error: None,
+            skipped: None,
+            system_out: None,
+            time: None,
         };
         let expected = TestSuite {
             name: name.clone(),
             errors: 0,
             failures: 0,
+            skipped: 0,
             tests: 1,
-            test_cases: vec![expected_test_case]
+            test_cases: vec![expected_test_case],
+            system_out: None,
+            time: None,
+            timestamp: None,
         };
         let suite = TestSuite::new(events, name).unwrap();
 
@@ -173,25 +432,37 @@ mod tests {
 { "type": "test", "name": "failed", "event": "failed", "stdout": "idk dawg" }
 { "type": "suite", "event": "ok", "passed": 1, "failed": 1, "allowed_fail": 0, "ignored": 0, "measured": 0, "filtered_out": 40 }"#;
 
-        let events = parse_test_results(stdout);
+        let events = parse_test_results(stdout).unwrap();
 
         let name = String::from("Doc Tests");
         let expected_test_case = TestCase {
             name: String::from("parsers::test::test_zpools_on_single_zpool"),
             failure: None,
+            error: None,
+            skipped: None,
+            system_out: None,
+            time: None,
         };
         let expected_test_case2 = TestCase {
             name: String::from("failed"),
             failure: Some(Failure {
                 message: String::from("idk dawg")
             }),
+            error: None,
+            skipped: None,
+            system_out: None,
+            time: None,
         };
         let expected = TestSuite {
             name: name.clone(),
             errors: 0,
             failures: 1,
+            skipped: 0,
             tests: 2,
-            test_cases: vec![expected_test_case, expected_test_case2]
+            test_cases: vec![expected_test_case, expected_test_case2],
+            system_out: None,
+            time: None,
+            timestamp: None,
         };
         let suite = TestSuite::new(events, name).unwrap();
 
@@ -209,7 +480,7 @@ mod tests {
 { "type": "suite", "event": "ok", "passed": 1, "failed": 1, "allowed_fail": 0, "ignored": 0, "measured": 0, "filtered_out": 40 }"#;
 
         let name = String::from("Doc Tests");
-        let events = parse_test_results(stdout);
+        let events = parse_test_results(stdout).unwrap();
         let suite = TestSuite::new(events, name).unwrap();
 
         let suites = vec![suite];
@@ -218,6 +489,224 @@ mod tests {
 
         write_as_xml(&suites, &mut output).unwrap();
     }
+    #[test]
+    fn test_ignored_output() {
+        let stdout = r#"{ "type": "suite", "event": "started", "test_count": 2 }
+{ "type": "test", "event": "started", "name": "works" }
+{ "type": "test", "name": "works", "event": "ok" }
+{ "type": "test", "name": "needs_zfs", "event": "ignored", "message": "requires root" }
+{ "type": "suite", "event": "ok", "passed": 1, "failed": 0, "allowed_fail": 0, "ignored": 1, "measured": 0, "filtered_out": 0 }"#;
+
+        let events = parse_test_results(stdout).unwrap();
+        let suite = TestSuite::new(events, String::from("Lib Tests")).unwrap();
+
+        assert_eq!(1, suite.skipped);
+        assert_eq!(2, suite.test_cases.len());
+        assert_eq!(Some(Skipped { message: Some(String::from("requires root")) }), suite.test_cases[1].skipped);
+
+        let mut output = Vec::with_capacity(128);
+        write_as_xml(&[suite], &mut output).unwrap();
+        let xml = String::from_utf8(output).unwrap();
+
+        assert!(xml.contains(r#"skipped="1""#));
+        assert!(xml.contains(r#"<skipped message="requires root">"#));
+    }
+
+    #[test]
+    fn test_builder_yields_finished_tests() {
+        let mut builder = TestSuiteBuilder::new(String::from("Lib Tests"));
+
+        let started = Event::new_test(EventKind::Started, String::from("works"));
+        assert!(builder.push(started).unwrap().is_none());
+        let finished = Event::new_test(EventKind::Ok, String::from("works"));
+        assert_eq!("works", builder.push(finished).unwrap().unwrap().name);
+
+        let suite = builder.finish(None);
+        assert_eq!(1, suite.test_cases.len());
+        assert_eq!(0, suite.errors);
+    }
+
+    #[test]
+    fn test_crashed_output() {
+        let stdout = r#"{ "type": "suite", "event": "started", "test_count": 3 }
+{ "type": "test", "event": "started", "name": "works" }
+{ "type": "test", "name": "works", "event": "ok" }
+{ "type": "test", "event": "started", "name": "segfaults" }"#;
+
+        let events = parse_test_results(stdout).unwrap();
+        let abort = Abort {
+            reason: String::from("terminated by signal 11 (SIGSEGV)"),
+            stderr: String::from("Segmentation fault"),
+            culprit: None,
+        };
+        let suite = TestSuite::with_abort(events, String::from("Lib Tests"), Some(abort)).unwrap();
+
+        assert_eq!(1, suite.errors);
+        // Third test never started, it isn't counted.
+        assert_eq!(2, suite.tests);
+        let expected_error = Error {
+            message: String::from("terminated by signal 11 (SIGSEGV)"),
+            output: Some(String::from("Segmentation fault")),
+        };
+        assert_eq!(String::from("segfaults"), suite.test_cases[1].name);
+        assert_eq!(Some(expected_error), suite.test_cases[1].error);
+
+        let mut output = Vec::with_capacity(128);
+        write_as_xml(&[suite], &mut output).unwrap();
+        let xml = String::from_utf8(output).unwrap();
+
+        assert!(xml.contains(r#"tests="2""#));
+        assert!(xml.contains(r#"errors="1""#));
+        assert!(xml.contains(r#"<error message="terminated by signal 11 (SIGSEGV)">Segmentation fault</error>"#));
+    }
+
+    #[test]
+    fn test_span() {
+        let mut suite = crashed_suite();
+        let (start, stop) = suite.span().unwrap();
+        // Killed suite has no duration of its own.
+        assert_eq!(Duration::from_millis(750), stop.duration_since(start).unwrap());
+
+        suite.time = Some(Duration::from_secs(1));
+        let (start, stop) = suite.span().unwrap();
+        assert_eq!(Duration::from_secs(1), stop.duration_since(start).unwrap());
+
+        suite.timestamp = None;
+        assert_eq!(None, suite.span());
+    }
+
+    #[test]
+    fn test_crashed_after_failed_test() {
+        let stdout = r#"{ "type": "suite", "event": "started", "test_count": 2 }
+{ "type": "test", "event": "started", "name": "broken" }
+{ "type": "test", "name": "broken", "event": "failed", "stdout": "assertion failed" }
+{ "type": "test", "event": "started", "name": "segfaults" }"#;
+
+        let events = parse_test_results(stdout).unwrap();
+        let abort = Abort {
+            reason: String::from("terminated by signal 11 (SIGSEGV)"),
+            stderr: String::new(),
+            culprit: None,
+        };
+        let suite = TestSuite::with_abort(events, String::from("Lib Tests"), Some(abort)).unwrap();
+
+        assert_eq!(1, suite.failures);
+        assert_eq!(1, suite.errors);
+    }
+
+    #[test]
+    fn test_timed_out_and_interrupted() {
+        let stdout = r#"{ "type": "suite", "event": "started", "test_count": 2 }
+{ "type": "test", "event": "started", "name": "hangs" }
+{ "type": "test", "event": "started", "name": "innocent" }"#;
+
+        let events = parse_test_results(stdout).unwrap();
+        let abort = Abort {
+            reason: String::from("test hangs timed out after 60s"),
+            stderr: String::new(),
+            culprit: Some(String::from("hangs")),
+        };
+        let suite = TestSuite::with_abort(events, String::from("Lib Tests"), Some(abort)).unwrap();
+
+        assert_eq!(2, suite.errors);
+        let messages: Vec<&str> = suite
+            .test_cases
+            .iter()
+            .map(|case| case.error.as_ref().unwrap().message.as_str())
+            .collect();
+        assert_eq!(
+            vec!["test hangs timed out after 60s", "interrupted: test hangs timed out after 60s"],
+            messages
+        );
+    }
+
+    #[test]
+    fn test_crashed_before_first_test() {
+        let abort = Abort {
+            reason: String::from("terminated by signal 6 (SIGABRT)"),
+            stderr: String::new(),
+            culprit: None,
+        };
+        let suite = TestSuite::with_abort(Vec::new(), String::from("Lib Tests"), Some(abort)).unwrap();
+
+        assert_eq!(1, suite.errors);
+        assert_eq!(1, suite.tests);
+        assert_eq!(String::from("Lib Tests"), suite.test_cases[0].name);
+    }
+
+    #[test]
+    fn test_slow_test_warning() {
+        let stdout = r#"{ "type": "suite", "event": "started", "test_count": 2 }
+{ "type": "test", "event": "started", "name": "slow" }
+{ "type": "test", "event": "timeout", "name": "slow" }
+{ "type": "test", "event": "started", "name": "hangs" }
+{ "type": "test", "event": "timeout", "name": "hangs" }
+{ "type": "test", "name": "slow", "event": "ok" }"#;
+
+        let events = parse_test_results(stdout).unwrap();
+        let abort = Abort {
+            reason: String::from("timed out after 300s"),
+            stderr: String::new(),
+            culprit: None,
+        };
+        let suite = TestSuite::with_abort(events, String::from("Lib Tests"), Some(abort)).unwrap();
+
+        assert_eq!(2, suite.test_cases.len());
+        assert_eq!(None, suite.test_cases[0].error);
+        assert_eq!(String::from("hangs"), suite.test_cases[1].name);
+        assert_eq!(1, suite.errors);
+    }
+
+    #[test]
+    fn test_suite_events_without_counts() {
+        let stdout = r#"{ "type": "suite", "event": "started" }
+{ "type": "suite", "event": "ok" }"#;
+
+        let events = parse_test_results(stdout).unwrap();
+        let suite = TestSuite::new(events, String::from("Lib Tests")).unwrap();
+
+        assert_eq!(0, suite.tests);
+        assert_eq!(0, suite.failures);
+    }
+
+    #[test]
+    fn test_failed_run_is_not_an_error() {
+        let stdout = r#"{ "type": "suite", "event": "started", "test_count": 1 }
+{ "type": "test", "event": "started", "name": "failed" }
+{ "type": "test", "name": "failed", "event": "failed", "stdout": "idk dawg" }
+{ "type": "suite", "event": "failed", "passed": 0, "failed": 1, "allowed_fail": 0, "ignored": 0, "measured": 0, "filtered_out": 0 }"#;
+
+        let events = parse_test_results(stdout).unwrap();
+        let abort = Abort {
+            reason: String::from("exited with status 101"),
+            stderr: String::new(),
+            culprit: None,
+        };
+        let suite = TestSuite::with_abort(events, String::from("Lib Tests"), Some(abort)).unwrap();
+
+        assert_eq!(0, suite.errors);
+        assert_eq!(1, suite.failures);
+    }
+
+    #[test]
+    fn test_generate_xml_with_time() {
+        let stdout = r#"{ "type": "suite", "event": "started", "test_count": 1 }
+{ "type": "test", "event": "started", "name": "slow" }
+{ "type": "test", "name": "slow", "event": "ok", "exec_time": 1.5 }
+{ "type": "suite", "event": "ok", "passed": 1, "failed": 0, "allowed_fail": 0, "ignored": 0, "measured": 0, "filtered_out": 0, "exec_time": 1.5004 }"#;
+
+        let events = parse_test_results(stdout).unwrap();
+        let suite = TestSuite::new(events, String::from("Lib Tests")).unwrap();
+        assert_eq!(Some(Duration::from_millis(1500)), suite.test_cases[0].time);
+
+        let mut output = Vec::with_capacity(128);
+        write_as_xml(&[suite], &mut output).unwrap();
+        let xml = String::from_utf8(output).unwrap();
+
+        assert!(xml.contains(r#"<testsuite name="Lib Tests" errors="0" failures="0" skipped="0" tests="1" time="1.500">"#));
+        assert!(xml.contains(r#"<testcase name="slow" time="1.500">"#));
+    }
+
     #[test]
     fn test_multiple_outputs() {
         let stdout = r#"{ "type": "suite", "event": "started", "test_count": 1 }
@@ -228,7 +717,7 @@ mod tests {
 { "type": "test", "event": "started", "name": "parsers::test::test_zpools_on_single_zpool" }
 { "type": "test", "name": "parsers::test::test_zpools_on_single_zpool", "event": "ok" }
 { "type": "suite", "event": "ok", "passed": 1, "failed": 0, "allowed_fail": 0, "ignored": 0, "measured": 0, "filtered_out": 40 }"#;
-        let events = parse_test_results(stdout);
+        let events = parse_test_results(stdout).unwrap();
         let suite = TestSuite::new(events, String::from("should fail"));
         assert!(suite.is_err());
     }
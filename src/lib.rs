@@ -5,5 +5,25 @@ extern crate serde_derive;
 pub mod results;
 pub mod junit;
 pub mod configuration;
+pub mod manifest;
+pub mod metadata;
 pub mod runspec;
-pub mod errors;
\ No newline at end of file
+pub mod errors;
+pub mod artifacts;
+pub mod runner;
+pub mod listener;
+pub mod scheduler;
+pub mod summary;
+pub mod report;
+pub mod tap;
+pub mod json;
+pub mod html;
+pub mod markdown;
+pub mod github;
+pub mod teamcity;
+pub mod trx;
+mod ids;
+mod panic;
+pub mod nunit;
+pub mod ctrf;
+pub mod allure;
@@ -0,0 +1,79 @@
+//! Hooks to observe a run while it's happening.
+
+use crate::junit::{TestCase, TestSuite};
+use crate::results::Event;
+
+/// Receives progress of a workflow as test processes report it. All methods do nothing by default.
+pub trait Listener {
+    /// Test process for a suite is about to be started.
+    fn suite_started(&mut self, _suite: &str) {}
+    /// Test process emitted an event.
+    fn event(&mut self, _suite: &str, _event: &Event) {}
+    /// Test has finished: passed, failed or got skipped.
+    fn test_finished(&mut self, _suite: &str, _test_case: &TestCase) {}
+    /// Test process for a suite has exited and suite is complete.
+    fn suite_finished(&mut self, _suite: &TestSuite) {}
+}
+
+/// Listener that ignores everything.
+impl Listener for () {}
+
+/// Both listeners receive everything, first one goes first.
+impl<A: Listener, B: Listener> Listener for (A, B) {
+    fn suite_started(&mut self, suite: &str) {
+        self.0.suite_started(suite);
+        self.1.suite_started(suite);
+    }
+    fn event(&mut self, suite: &str, event: &Event) {
+        self.0.event(suite, event);
+        self.1.event(suite, event);
+    }
+    fn test_finished(&mut self, suite: &str, test_case: &TestCase) {
+        self.0.test_finished(suite, test_case);
+        self.1.test_finished(suite, test_case);
+    }
+    fn suite_finished(&mut self, suite: &TestSuite) {
+        self.0.suite_finished(suite);
+        self.1.suite_finished(suite);
+    }
+}
+
+/// Boxed listeners receive everything.
+impl<L: Listener + ?Sized> Listener for Box<L> {
+    fn suite_started(&mut self, suite: &str) {
+        (**self).suite_started(suite);
+    }
+    fn event(&mut self, suite: &str, event: &Event) {
+        (**self).event(suite, event);
+    }
+    fn test_finished(&mut self, suite: &str, test_case: &TestCase) {
+        (**self).test_finished(suite, test_case);
+    }
+    fn suite_finished(&mut self, suite: &TestSuite) {
+        (**self).suite_finished(suite);
+    }
+}
+
+/// Every listener receives everything, in order.
+impl<L: Listener> Listener for Vec<L> {
+    fn suite_started(&mut self, suite: &str) {
+        for listener in self.iter_mut() {
+            listener.suite_started(suite);
+        }
+    }
+    fn event(&mut self, suite: &str, event: &Event) {
+        for listener in self.iter_mut() {
+            listener.event(suite, event);
+        }
+    }
+    fn test_finished(&mut self, suite: &str, test_case: &TestCase) {
+        for listener in self.iter_mut() {
+            listener.test_finished(suite, test_case);
+        }
+    }
+    fn suite_finished(&mut self, suite: &TestSuite) {
+        for listener in self.iter_mut() {
+            listener.suite_finished(suite);
+        }
+    }
+}
@@ -0,0 +1,108 @@
+//! Parts of crate's `Cargo.toml` suity cares about.
+
+use crate::errors::SuityError;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct Manifest {
+    /// `[features]` table.
+    #[serde(default)]
+    pub features: BTreeMap<String, Vec<String>>,
+    /// `[package]` table.
+    pub package: Option<Section>,
+    /// `[workspace]` table.
+    pub workspace: Option<Section>,
+}
+
+/// `[package]` or `[workspace]` table, only metadata of tools is read.
+#[derive(Debug, Default, Deserialize)]
+pub struct Section {
+    #[serde(default)]
+    pub metadata: toml::value::Table,
+}
+
+impl Section {
+    fn suity(&self) -> Option<&toml::Value> {
+        self.metadata.get("suity")
+    }
+}
+
+impl Manifest {
+    pub fn from_path(path: &Path) -> Result<Manifest, SuityError> {
+        let contents = fs::read_to_string(path)?;
+        toml::from_str(&contents).map_err(SuityError::FailedToParseManifest)
+    }
+
+    /// `suity` table of `[package.metadata]`, or of `[workspace.metadata]` if package has none.
+    pub fn suity_metadata(&self) -> Option<&toml::Value> {
+        let package = self.package.as_ref().and_then(Section::suity);
+        package.or_else(|| self.workspace.as_ref().and_then(Section::suity))
+    }
+
+    /// Names of features crate declares, `default` is not a feature on its own.
+    pub fn feature_names(&self) -> Vec<String> {
+        self.features
+            .keys()
+            .filter(|name| name.as_str() != "default")
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Manifest;
+
+    #[test]
+    fn feature_names() {
+        let manifest: Manifest = toml::from_str(
+            r#"
+[package]
+name = "crashy"
+
+[features]
+default = ["serde-json"]
+serde-json = ["serde_json"]
+tokio = []
+"#,
+        )
+        .unwrap();
+        assert_eq!(vec!["serde-json", "tokio"], manifest.feature_names());
+    }
+
+    #[test]
+    fn package_metadata_over_workspace_metadata() {
+        let manifest: Manifest = toml::from_str(
+            r#"
+[package]
+name = "crashy"
+
+[package.metadata.suity.workflow.default]
+doc = false
+
+[workspace.metadata.suity.workflow.everything]
+workspace = true
+"#,
+        )
+        .unwrap();
+        let suity = manifest.suity_metadata().unwrap();
+        assert!(suity.get("workflow").unwrap().get("default").is_some());
+
+        let workspace: Manifest = toml::from_str(
+            r#"
+[workspace]
+members = ["core"]
+
+[workspace.metadata.suity.workflow.everything]
+workspace = true
+"#,
+        )
+        .unwrap();
+        assert!(workspace.suity_metadata().unwrap().get("workflow").unwrap().get("everything").is_some());
+
+        let plain: Manifest = toml::from_str("[package]\nname = \"crashy\"\n").unwrap();
+        assert!(plain.suity_metadata().is_none());
+    }
+}
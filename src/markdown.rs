@@ -0,0 +1,173 @@
+//! Support for export as a Markdown summary, e.g. for PR comments or CI job summaries.
+
+use crate::configuration::RunSettings;
+use crate::errors::SuityError;
+use crate::report::{create_file, RunReporter, WorkflowResults};
+use std::env;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::Path;
+
+/// Name of the summary within output directory of a run.
+pub const FILE_NAME: &str = "report.md";
+
+/// Writes Markdown summary of a whole run.
+pub struct MarkdownReporter;
+
+impl RunReporter for MarkdownReporter {
+    /// Besides `report.md` in `output`, summary is appended to the file named by environment
+    /// variable in `step_summary_env` of the run, if that variable is set.
+    fn report_run(&self, output: &Path, settings: &RunSettings, workflows: &[WorkflowResults]) -> Result<(), SuityError> {
+        let mut file = create_file(&output.join(FILE_NAME))?;
+        write_as_markdown(workflows, &mut file)?;
+        file.flush()?;
+
+        let step_summary = settings
+            .step_summary_env
+            .as_ref()
+            .and_then(env::var_os)
+            .filter(|path| !path.is_empty());
+        if let Some(path) = step_summary {
+            let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+            write_as_markdown(workflows, &mut file)?;
+        }
+        Ok(())
+    }
+}
+
+pub fn write_as_markdown<W: Write>(workflows: &[WorkflowResults], mut writer: W) -> io::Result<()> {
+    writeln!(writer, "## Test results")?;
+    for workflow in workflows {
+        writeln!(writer)?;
+        let suites = match workflow.suites {
+            Some(suites) => suites,
+            None => {
+                writeln!(writer, "### {}: didn't compile", workflow.runspec.name)?;
+                continue;
+            }
+        };
+        let failed: u64 = suites.iter().map(|s| s.failures + s.errors).sum();
+        let status = if failed == 0 { "passed" } else { "failed" };
+        writeln!(writer, "### {}: {}", workflow.runspec.name, status)?;
+        writeln!(writer)?;
+        writeln!(writer, "| Suite | Tests | Passed | Failed | Skipped | Duration |")?;
+        writeln!(writer, "|-------|------:|-------:|-------:|--------:|---------:|")?;
+        for suite in suites {
+            let failed = suite.failures + suite.errors;
+            let duration = suite
+                .time
+                .map(|time| format!("{:.3}s", time.as_secs_f64()))
+                .unwrap_or_default();
+            writeln!(
+                writer,
+                "| {} | {} | {} | {} | {} | {} |",
+                escape_cell(&suite.name),
+                suite.tests,
+                suite.tests.saturating_sub(failed + suite.skipped),
+                failed,
+                suite.skipped,
+                duration
+            )?;
+        }
+        for suite in suites {
+            for case in &suite.test_cases {
+                let message = match (&case.failure, &case.error) {
+                    (Some(failure), _) => failure.message.clone(),
+                    (None, Some(error)) => match error.output {
+                        Some(ref output) => format!("{}\n\n{}", error.message, output),
+                        None => error.message.clone(),
+                    },
+                    (None, None) => continue,
+                };
+                let fence = fence_for(&message);
+                writeln!(writer)?;
+                writeln!(writer, "<details>")?;
+                writeln!(
+                    writer,
+                    "<summary>{} {}</summary>",
+                    escape_html(&suite.name),
+                    escape_html(&case.name)
+                )?;
+                writeln!(writer)?;
+                writeln!(writer, "{}", fence)?;
+                writeln!(writer, "{}", message.trim_end())?;
+                writeln!(writer, "{}", fence)?;
+                writeln!(writer)?;
+                writeln!(writer, "</details>")?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn escape_cell(text: &str) -> String {
+    text.replace('|', "\\|")
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+/// Code fence that is longer than any run of backticks in the text, so text can't close it.
+fn fence_for(text: &str) -> String {
+    let longest = text
+        .split(|c| c != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or(0);
+    "`".repeat(longest.max(2) + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::write_as_markdown;
+    use crate::junit::TestSuite;
+    use crate::report::WorkflowResults;
+    use crate::results::parse_test_results;
+    use crate::runspec::Runspec;
+
+    #[test]
+    fn workflow_tables_and_failures() {
+        let stdout = r#"{ "type": "suite", "event": "started", "test_count": 3 }
+{ "type": "test", "event": "started", "name": "works" }
+{ "type": "test", "name": "works", "event": "ok" }
+{ "type": "test", "event": "started", "name": "broken" }
+{ "type": "test", "name": "broken", "event": "failed", "stdout": "left: ```1```\n" }
+{ "type": "test", "name": "needs_zfs", "event": "ignored" }
+{ "type": "suite", "event": "failed", "passed": 1, "failed": 1, "allowed_fail": 0, "ignored": 1, "measured": 0, "filtered_out": 0, "exec_time": 0.5 }"#;
+        let suites = vec![TestSuite::new(parse_test_results(stdout).unwrap(), String::from("[default] Lib-tests")).unwrap()];
+        let default = Runspec::default();
+        let serde = Runspec {
+            name: String::from("serde"),
+            ..Runspec::default()
+        };
+        let workflows = [
+            WorkflowResults { runspec: &default, suites: Some(&suites) },
+            WorkflowResults { runspec: &serde, suites: None },
+        ];
+
+        let mut output = Vec::new();
+        write_as_markdown(&workflows, &mut output).unwrap();
+
+        let expected = r#"## Test results
+
+### default: failed
+
+| Suite | Tests | Passed | Failed | Skipped | Duration |
+|-------|------:|-------:|-------:|--------:|---------:|
+| [default] Lib-tests | 3 | 1 | 1 | 1 | 0.500s |
+
+<details>
+<summary>[default] Lib-tests broken</summary>
+
+````
+left: ```1```
+````
+
+</details>
+
+### serde: didn't compile
+"#;
+        assert_eq!(expected, String::from_utf8(output).unwrap());
+    }
+}
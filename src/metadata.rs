@@ -0,0 +1,98 @@
+//! Support for `cargo metadata` output, used to find packages of a workspace.
+
+use crate::errors::SuityError;
+use std::collections::BTreeMap;
+use std::process::{Command, Stdio};
+
+/// Build target of a package.
+#[derive(Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct Target {
+    pub name: String,
+    /// Kinds of the target: `lib`, `proc-macro`, `bin`, `test`, `example`, `bench`, etc.
+    pub kind: Vec<String>,
+}
+
+impl Target {
+    pub fn has_kind(&self, kind: &str) -> bool {
+        self.kind.iter().any(|k| k == kind)
+    }
+
+    /// Library targets are the ones `--lib` and `--doc` test.
+    pub fn is_lib(&self) -> bool {
+        self.kind
+            .iter()
+            .any(|k| matches!(k.as_str(), "lib" | "rlib" | "dylib" | "cdylib" | "staticlib" | "proc-macro"))
+    }
+}
+
+/// Member of a workspace.
+#[derive(Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct Package {
+    pub name: String,
+    /// Same id cargo puts into `package_id` of build messages.
+    pub id: String,
+    pub targets: Vec<Target>,
+    /// `[features]` of the package.
+    #[serde(default)]
+    pub features: BTreeMap<String, Vec<String>>,
+}
+
+impl Package {
+    pub fn has_lib(&self) -> bool {
+        self.targets.iter().any(Target::is_lib)
+    }
+
+    /// Whether package has target `name` of `kind`.
+    pub fn has_target(&self, kind: &str, name: &str) -> bool {
+        self.targets.iter().any(|target| target.has_kind(kind) && target.name == name)
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Metadata {
+    /// Only members of the workspace, dependencies are never asked for.
+    pub packages: Vec<Package>,
+}
+
+impl Metadata {
+    /// Run `cargo metadata` in current directory.
+    pub fn load() -> Result<Metadata, SuityError> {
+        let out = Command::new("cargo")
+            .args(["metadata", "--no-deps", "--format-version", "1"])
+            .stderr(Stdio::inherit())
+            .output()?;
+        if !out.status.success() {
+            return Err(SuityError::FailedToReadMetadata {
+                message: format!("cargo metadata exited with {}", out.status),
+            });
+        }
+        Metadata::parse(&String::from_utf8_lossy(&out.stdout))
+    }
+
+    pub fn parse(stdout: &str) -> Result<Metadata, SuityError> {
+        serde_json::from_str(stdout).map_err(|e| SuityError::FailedToReadMetadata { message: e.to_string() })
+    }
+
+    pub fn package(&self, name: &str) -> Option<&Package> {
+        self.packages.iter().find(|package| package.name == name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Metadata;
+
+    #[test]
+    fn parse_workspace_members() {
+        let stdout = r#"{"packages":[{"name":"core","version":"0.1.0","id":"path+file:///ws/core#0.1.0","dependencies":[],"targets":[{"kind":["lib"],"crate_types":["lib"],"name":"core","src_path":"/ws/core/src/lib.rs","edition":"2018","doctest":true,"test":true},{"kind":["test"],"crate_types":["bin"],"name":"api","src_path":"/ws/core/tests/api.rs","edition":"2018","doctest":false,"test":true}],"features":{"default":["serde"],"serde":[]},"manifest_path":"/ws/core/Cargo.toml"},{"name":"cli","version":"0.1.0","id":"path+file:///ws/cli#0.1.0","dependencies":[],"targets":[{"kind":["bin"],"crate_types":["bin"],"name":"cli","src_path":"/ws/cli/src/main.rs","edition":"2018","doctest":false,"test":true}],"features":{},"manifest_path":"/ws/cli/Cargo.toml"}],"workspace_members":["path+file:///ws/core#0.1.0","path+file:///ws/cli#0.1.0"],"resolve":null,"target_directory":"/ws/target","version":1,"workspace_root":"/ws"}"#;
+        let metadata = Metadata::parse(stdout).unwrap();
+
+        assert_eq!(2, metadata.packages.len());
+        let core = metadata.package("core").unwrap();
+        assert_eq!("path+file:///ws/core#0.1.0", core.id);
+        assert!(core.has_lib());
+        assert!(core.has_target("test", "api"));
+        assert!(!metadata.package("cli").unwrap().has_lib());
+        assert!(metadata.package("nope").is_none());
+    }
+}
@@ -0,0 +1,172 @@
+//! Support for export in NUnit 3 XML format.
+
+use crate::junit::{TestCase, TestSuite};
+use crate::panic::summarize_failure;
+use crate::report::FileReporter;
+use crate::runspec::Runspec;
+use std::io::{self, Write};
+use std::time::Duration;
+use xml_writer::XmlWriter;
+
+/// Writes NUnit 3 report.
+pub struct NUnit3Reporter;
+
+impl FileReporter for NUnit3Reporter {
+    fn extension(&self) -> &'static str {
+        "nunit.xml"
+    }
+
+    fn write(&self, runspec: &Runspec, suites: &[TestSuite], output: &mut dyn Write) -> io::Result<()> {
+        write_as_nunit3(&runspec.name, suites, output)
+    }
+}
+
+/// `result` and optional `label` of a test.
+fn result(case: &TestCase) -> (&'static str, Option<&'static str>) {
+    if case.failure.is_some() {
+        ("Failed", None)
+    } else if case.error.is_some() {
+        ("Failed", Some("Error"))
+    } else if case.skipped.is_some() {
+        ("Skipped", Some("Ignored"))
+    } else {
+        ("Passed", None)
+    }
+}
+
+fn passed_or_failed(failed: u64) -> &'static str {
+    if failed == 0 {
+        "Passed"
+    } else {
+        "Failed"
+    }
+}
+
+fn format_duration(duration: Option<Duration>) -> String {
+    format!("{:.6}", duration.map(|d| d.as_secs_f64()).unwrap_or(0.0))
+}
+
+/// Counts of a set of suites: total, passed, failed, skipped.
+fn counts<'a, I: Iterator<Item = &'a TestSuite>>(suites: I) -> (u64, u64, u64, u64) {
+    suites.fold((0, 0, 0, 0), |(total, passed, failed, skipped), suite| {
+        let suite_failed = suite.failures + suite.errors;
+        (
+            total + suite.tests,
+            passed + suite.tests.saturating_sub(suite_failed + suite.skipped),
+            failed + suite_failed,
+            skipped + suite.skipped,
+        )
+    })
+}
+
+fn write_counts<W: Write>(xml: &mut XmlWriter<W>, (total, passed, failed, skipped): (u64, u64, u64, u64)) -> io::Result<()> {
+    xml.attr("testcasecount", &total.to_string())?;
+    xml.attr("result", passed_or_failed(failed))?;
+    xml.attr("total", &total.to_string())?;
+    xml.attr("passed", &passed.to_string())?;
+    xml.attr("failed", &failed.to_string())?;
+    xml.attr("inconclusive", "0")?;
+    xml.attr("skipped", &skipped.to_string())?;
+    Ok(())
+}
+
+pub fn write_as_nunit3<W: Write>(name: &str, suites: &[TestSuite], writer: W) -> io::Result<()> {
+    let mut xml = XmlWriter::new(writer);
+    xml.dtd("utf-8")?;
+    xml.begin_elem("test-run")?;
+    xml.attr("id", "0")?;
+    xml.attr_esc("name", name)?;
+    xml.attr_esc("fullname", name)?;
+    write_counts(&mut xml, counts(suites.iter()))?;
+    let total_time: Duration = suites.iter().filter_map(|suite| suite.time).sum();
+    xml.attr("duration", &format_duration(Some(total_time)))?;
+
+    for (suite_idx, suite) in suites.iter().enumerate() {
+        let suite_id = format!("{}", suite_idx + 1);
+        xml.begin_elem("test-suite")?;
+        xml.attr("type", "TestSuite")?;
+        xml.attr("id", &suite_id)?;
+        xml.attr_esc("name", &suite.name)?;
+        xml.attr_esc("fullname", &suite.name)?;
+        write_counts(&mut xml, counts(std::iter::once(suite)))?;
+        xml.attr("duration", &format_duration(suite.time))?;
+        if let Some(ref output) = suite.system_out {
+            xml.elem_text("output", output)?;
+        }
+        for (case_idx, case) in suite.test_cases.iter().enumerate() {
+            write_test_case(&mut xml, &format!("{}-{}", suite_id, case_idx + 1), suite, case)?;
+        }
+        xml.end_elem()?;
+    }
+
+    xml.end_elem()?;
+    xml.close()?;
+    xml.flush()
+}
+
+fn write_test_case<W: Write>(xml: &mut XmlWriter<W>, id: &str, suite: &TestSuite, case: &TestCase) -> io::Result<()> {
+    let (result, label) = result(case);
+    xml.begin_elem("test-case")?;
+    xml.attr("id", id)?;
+    xml.attr_esc("name", &case.name)?;
+    xml.attr_esc("fullname", &format!("{}.{}", suite.name, case.name))?;
+    xml.attr_esc("classname", &suite.name)?;
+    xml.attr("result", result)?;
+    if let Some(label) = label {
+        xml.attr("label", label)?;
+    }
+    xml.attr("duration", &format_duration(case.time))?;
+    xml.attr("asserts", "0")?;
+
+    if let Some(ref skipped) = case.skipped {
+        xml.begin_elem("reason")?;
+        xml.elem_text("message", skipped.message.as_deref().unwrap_or(""))?;
+        xml.end_elem()?;
+    }
+    // Captured stdout of a failed test is where the panic message is.
+    let output = if let Some(ref failure) = case.failure {
+        xml.begin_elem("failure")?;
+        xml.elem_text("message", &summarize_failure(&failure.message))?;
+        xml.end_elem()?;
+        Some(failure.message.as_str())
+    } else if let Some(ref error) = case.error {
+        xml.begin_elem("failure")?;
+        xml.elem_text("message", &error.message)?;
+        if let Some(ref stderr) = error.output {
+            xml.elem_text("stack-trace", stderr)?;
+        }
+        xml.end_elem()?;
+        case.system_out.as_deref()
+    } else {
+        case.system_out.as_deref()
+    };
+    if let Some(output) = output {
+        xml.elem_text("output", output)?;
+    }
+    xml.end_elem()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::write_as_nunit3;
+    use crate::junit::crashed_suite;
+
+    #[test]
+    fn results_and_output() {
+        let suite = crashed_suite();
+
+        let mut output = Vec::new();
+        write_as_nunit3("default", &[suite], &mut output).unwrap();
+        let xml = String::from_utf8(output).unwrap();
+
+        assert!(xml.contains(r#"<test-run id="0" name="default" fullname="default" testcasecount="4" result="Failed" total="4" passed="1" failed="2" inconclusive="0" skipped="1""#));
+        assert!(xml.contains(r#"name="works" fullname="[default] Lib-tests.works" classname="[default] Lib-tests" result="Passed" duration="0.250000""#));
+        assert!(xml.contains(r#"name="broken" fullname="[default] Lib-tests.broken" classname="[default] Lib-tests" result="Failed" duration="0.500000""#));
+        assert!(xml.contains("<message>boom</message>"));
+        assert!(xml.contains("<output>thread &apos;broken&apos; panicked at &apos;boom&apos;, src/lib.rs:7:5\n</output>"));
+        assert!(xml.contains(r#"result="Skipped" label="Ignored""#));
+        assert!(xml.contains("<message>requires root</message>"));
+        assert!(xml.contains(r#"result="Failed" label="Error""#));
+        assert!(xml.contains("<stack-trace>oops</stack-trace>"));
+    }
+}
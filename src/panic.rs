@@ -0,0 +1,108 @@
+//! Panics in captured output of failed tests, for reports that show a short failure message.
+
+/// First panic found in captured output of a test.
+#[derive(Debug, Eq, PartialEq)]
+pub(crate) struct Panic {
+    pub file: String,
+    pub line: u32,
+    pub message: String,
+}
+
+const PANICKED_AT: &str = "panicked at ";
+
+/// Find first panic in output. Understands both `panicked at 'message', file:line:col` and
+/// newer `panicked at file:line:col:` followed by message on the next lines.
+pub(crate) fn find_panic(output: &str) -> Option<Panic> {
+    let start = output.find(PANICKED_AT)? + PANICKED_AT.len();
+    let rest = &output[start..];
+    if let Some(quoted) = rest.strip_prefix('\'') {
+        // Message may span lines, location is after the closing quote at the end of a line.
+        let mut offset = 0;
+        for line in quoted.split('\n') {
+            if let Some(idx) = line.rfind("', ") {
+                if let Some((file, line_no)) = parse_location(&line[idx + 3..]) {
+                    return Some(Panic {
+                        file,
+                        line: line_no,
+                        message: quoted[..offset + idx].to_string(),
+                    });
+                }
+            }
+            offset += line.len() + 1;
+        }
+        None
+    } else {
+        let (location, message) = match rest.find('\n') {
+            Some(idx) => (&rest[..idx], &rest[idx + 1..]),
+            None => (rest, ""),
+        };
+        let (file, line) = parse_location(location.trim_end().trim_end_matches(':'))?;
+        let message = message
+            .lines()
+            .take_while(|line| !line.starts_with("note: ") && !line.starts_with("thread '"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        Some(Panic { file, line, message })
+    }
+}
+
+/// Short description of a failure: panic message if there is one, otherwise first line of output.
+pub(crate) fn summarize_failure(output: &str) -> String {
+    match find_panic(output) {
+        Some(panic) => panic.message,
+        None => output
+            .lines()
+            .find(|line| !line.trim().is_empty())
+            .unwrap_or("")
+            .to_string(),
+    }
+}
+
+/// Parse `file:line:column`.
+fn parse_location(location: &str) -> Option<(String, u32)> {
+    let mut parts = location.trim().rsplitn(3, ':');
+    parts.next()?.parse::<u32>().ok()?;
+    let line = parts.next()?.parse::<u32>().ok()?;
+    let file = parts.next().filter(|file| !file.is_empty())?;
+    Some((file.to_string(), line))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_panic, summarize_failure, Panic};
+
+    #[test]
+    fn quoted_panic_message() {
+        let stdout = "thread 'test_status' panicked at 'assertion failed: `(left == right)`\n  left: `1`,\n right: `2`', tests/test_zpool.rs:401:9\nthread 'test_status' panicked at 'called `Result::unwrap()` on an `Err` value: Any', src/libcore/result.rs:1009:5\n";
+
+        let expected = Panic {
+            file: String::from("tests/test_zpool.rs"),
+            line: 401,
+            message: String::from("assertion failed: `(left == right)`\n  left: `1`,\n right: `2`"),
+        };
+        assert_eq!(Some(expected), find_panic(stdout));
+    }
+
+    #[test]
+    fn location_before_message() {
+        let stdout = "\nthread 'broken' panicked at tests/boom.rs:12:5:\nassertion `left == right` failed\n  left: 1\n right: 2\nnote: run with `RUST_BACKTRACE=1` environment variable to display a backtrace\n";
+
+        let expected = Panic {
+            file: String::from("tests/boom.rs"),
+            line: 12,
+            message: String::from("assertion `left == right` failed\n  left: 1\n right: 2"),
+        };
+        assert_eq!(Some(expected), find_panic(stdout));
+    }
+
+    #[test]
+    fn no_panic() {
+        assert_eq!(None, find_panic("Error: Os { code: 2 }\n"));
+    }
+
+    #[test]
+    fn summary_without_panic() {
+        assert_eq!("Error: NotFound", summarize_failure("\nError: NotFound\nmore\n"));
+        assert_eq!("boom", summarize_failure("thread 'broken' panicked at 'boom', tests/boom.rs:7:5\n"));
+    }
+}
@@ -0,0 +1,83 @@
+//! Reports written once a workflow or a whole run is complete.
+
+use crate::configuration::RunSettings;
+use crate::errors::SuityError;
+use crate::junit::TestSuite;
+use crate::runspec::{OutputFormat, Runspec};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Turns results of a workflow into a report, once the workflow is complete.
+pub trait Reporter {
+    /// Write report of a workflow.
+    fn report(&self, runspec: &Runspec, suites: &[TestSuite]) -> Result<(), SuityError>;
+}
+
+/// Reporter writing a single file per workflow into `output` directory of the workflow.
+pub trait FileReporter {
+    /// Extension of report file, `<output>/<workflow>.<extension>`. Must be unique among formats,
+    /// so reports of different formats can be written next to each other.
+    fn extension(&self) -> &'static str;
+
+    /// Write report of a workflow.
+    fn write(&self, runspec: &Runspec, suites: &[TestSuite], output: &mut dyn Write) -> io::Result<()>;
+
+    /// Where report of a workflow is written.
+    fn output_file_path(&self, runspec: &Runspec) -> PathBuf {
+        runspec.output.join(format!("{}.{}", runspec.name, self.extension()))
+    }
+}
+
+impl<R: FileReporter> Reporter for R {
+    fn report(&self, runspec: &Runspec, suites: &[TestSuite]) -> Result<(), SuityError> {
+        let mut file = create_file(&self.output_file_path(runspec))?;
+        self.write(runspec, suites, &mut file)?;
+        file.flush()?;
+        Ok(())
+    }
+}
+
+/// Turns results of all workflows of a run into a single report, once the run is complete.
+pub trait RunReporter {
+    /// Write report covering all workflows of a run that use this format into `output`.
+    fn report_run(&self, output: &Path, settings: &RunSettings, workflows: &[WorkflowResults]) -> Result<(), SuityError>;
+}
+
+/// Results of a single workflow within a run.
+#[derive(Debug, Copy, Clone)]
+pub struct WorkflowResults<'a> {
+    pub runspec: &'a Runspec,
+    /// `None` if tests of the workflow didn't compile.
+    pub suites: Option<&'a [TestSuite]>,
+}
+
+/// Write run-wide reports for every run-wide format used by at least one of the workflows.
+pub fn report_run(output: &Path, settings: &RunSettings, workflows: &[WorkflowResults]) -> Result<(), SuityError> {
+    let mut formats: Vec<OutputFormat> = Vec::new();
+    for format in workflows.iter().flat_map(|w| &w.runspec.format) {
+        if !formats.contains(format) {
+            formats.push(*format);
+        }
+    }
+    for format in formats {
+        let selected: Vec<WorkflowResults> = workflows
+            .iter()
+            .filter(|w| w.runspec.format.contains(&format))
+            .copied()
+            .collect();
+        if let Some(reporter) = format.run_reporter() {
+            reporter.report_run(output, settings, &selected)?;
+        }
+    }
+    Ok(())
+}
+
+/// Create file for writing, along with missing parent directories.
+pub fn create_file(path: &Path) -> io::Result<io::BufWriter<fs::File>> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let file = fs::File::create(path)?;
+    Ok(io::BufWriter::new(file))
+}
@@ -1,3 +1,5 @@
+use crate::errors::SuityError;
+
 /// Type of event generated by test runner
 #[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
 pub enum ItemKind {
@@ -14,9 +16,11 @@ pub enum EventKind {
     Ok,
     Failed,
     Ignored,
+    /// Test has been running for over 60 seconds. Only a warning, the test keeps running.
+    Timeout,
 }
 
-#[derive(Deserialize, Debug, Eq, PartialEq)]
+#[derive(Deserialize, Debug, PartialEq)]
 pub struct Suite {
     pub event: EventKind,
     pub test_count: Option<u64>,
@@ -26,15 +30,26 @@ pub struct Suite {
     pub ignored: Option<u64>,
     pub measured: Option<u64>,
     pub filtered_out: Option<u64>,
+    /// Output that couldn't be attributed to any test. Only populated by lenient parser.
+    #[serde(default)]
+    pub stdout: Option<String>,
+    /// Duration of the whole suite in seconds. Only reported with `--report-time`.
+    #[serde(default, deserialize_with = "exec_time::deserialize")]
+    pub exec_time: Option<f64>,
 }
 
-#[derive(Deserialize, Debug, Eq, PartialEq)]
+#[derive(Deserialize, Debug, PartialEq)]
 pub struct Test {
     pub event: EventKind,
     pub name: String,
     pub stdout: Option<String>,
+    /// Reason given to `#[ignore = "..."]`, if any.
+    pub message: Option<String>,
+    /// Duration of the test in seconds. Only reported with `--report-time`.
+    #[serde(default, deserialize_with = "exec_time::deserialize")]
+    pub exec_time: Option<f64>,
 }
-#[derive(Deserialize, Debug, Eq, PartialEq)]
+#[derive(Deserialize, Debug, PartialEq)]
 #[serde(tag = "type", rename_all = "lowercase")]
 pub enum Event {
     Suite(Suite),
@@ -53,6 +68,8 @@ impl Event {
             ignored: None,
             measured: None,
             filtered_out: None,
+            stdout: None,
+            exec_time: None,
         })
     }
 
@@ -119,31 +136,174 @@ impl Event {
             event,
             name,
             stdout: None,
+            message: None,
+            exec_time: None,
         })
     }
     #[cfg(test)]
     pub(crate) fn set_stdout(mut self, stdout: String) -> Event {
         match self {
             Event::Test(ref mut t) => t.stdout = Some(stdout),
-            _ => panic!("trying to set stdout on Event::Suite"),
+            Event::Suite(ref mut s) => s.stdout = Some(stdout),
+        };
+        self
+    }
+    #[cfg(test)]
+    pub(crate) fn set_message(mut self, message: String) -> Event {
+        match self {
+            Event::Test(ref mut t) => t.message = Some(message),
+            _ => panic!("trying to set message on Event::Suite"),
+        };
+        self
+    }
+    #[cfg(test)]
+    pub(crate) fn set_exec_time(mut self, exec_time: f64) -> Event {
+        match self {
+            Event::Test(ref mut t) => t.exec_time = Some(exec_time),
+            Event::Suite(ref mut s) => s.exec_time = Some(exec_time),
         };
         self
     }
 }
 
-pub fn parse_test_results(stdout: &str) -> Vec<Event> {
-    stdout
-        .lines()
-        .map(serde_json::from_str)
-        .map(Result::unwrap)
-        .collect()
+mod exec_time {
+    use serde::de::{Deserialize, Deserializer, Error};
+
+    /// Older toolchains report `"0.123s"`, newer ones plain number of seconds.
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum ExecTime {
+        Seconds(f64),
+        Formatted(String),
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<f64>, D::Error> {
+        match Option::<ExecTime>::deserialize(deserializer)? {
+            None => Ok(None),
+            Some(ExecTime::Seconds(seconds)) => Ok(Some(seconds)),
+            Some(ExecTime::Formatted(formatted)) => formatted
+                .trim_end_matches('s')
+                .parse()
+                .map(Some)
+                .map_err(D::Error::custom),
+        }
+    }
+}
+
+/// Incremental parser of libtest's JSON output. Fed one line at a time as test process produces
+/// them.
+///
+/// In lenient mode lines that aren't known events (e.g. output of tests running with
+/// `--nocapture`) are attached as captured output to the test that was running at the time, or to
+/// the suite if no test was running. In strict mode such lines are an error.
+#[derive(Debug, Default)]
+pub struct Parser {
+    lenient: bool,
+    line: usize,
+    /// Tests that have started, but not finished yet, with output captured so far.
+    running: Vec<(String, String)>,
+    orphaned: String,
+}
+
+impl Parser {
+    pub fn new(lenient: bool) -> Parser {
+        Parser {
+            lenient,
+            ..Parser::default()
+        }
+    }
+
+    /// Parse next line of output. Returns event if line completed one.
+    pub fn push(&mut self, line: &str) -> Result<Option<Event>, SuityError> {
+        self.line += 1;
+        if line.trim().is_empty() {
+            return Ok(None);
+        }
+        let event = match serde_json::from_str(line) {
+            Ok(event) => event,
+            Err(_) if self.lenient => {
+                let captured = match self.running.last_mut() {
+                    Some((_, captured)) => captured,
+                    None => &mut self.orphaned,
+                };
+                captured.push_str(line);
+                captured.push('\n');
+                return Ok(None);
+            }
+            Err(_) => {
+                return Err(SuityError::MalformedTestOutput {
+                    line: self.line,
+                    text: String::from(line),
+                })
+            }
+        };
+        if !self.lenient {
+            return Ok(Some(event));
+        }
+        let event = match event {
+            Event::Test(t) if t.event == EventKind::Started => {
+                self.running.push((t.name.clone(), String::new()));
+                Event::Test(t)
+            }
+            // Test keeps running, its output is still to come.
+            Event::Test(t) if t.event == EventKind::Timeout => Event::Test(t),
+            Event::Test(mut t) => {
+                if let Some(pos) = self.running.iter().position(|(name, _)| name == &t.name) {
+                    let (_, captured) = self.running.remove(pos);
+                    t.stdout = append_output(captured, t.stdout);
+                }
+                Event::Test(t)
+            }
+            Event::Suite(mut s) => {
+                if s.event != EventKind::Started {
+                    s.stdout = append_output(self.orphaned.split_off(0), s.stdout);
+                }
+                Event::Suite(s)
+            }
+        };
+        Ok(Some(event))
+    }
+
+    /// Output that wasn't attached to any event, because the run never finished.
+    pub fn finish(self) -> Option<String> {
+        let mut orphaned = self.orphaned;
+        for (_, captured) in self.running {
+            orphaned.push_str(&captured);
+        }
+        append_output(orphaned, None)
+    }
+}
+
+/// Parse libtest's JSON output. Fails on the first line that isn't a known event.
+pub fn parse_test_results(stdout: &str) -> Result<Vec<Event>, SuityError> {
+    let mut parser = Parser::new(false);
+    let mut events = Vec::new();
+    for line in stdout.lines() {
+        events.extend(parser.push(line)?);
+    }
+    Ok(events)
+}
+
+fn append_output(captured: String, stdout: Option<String>) -> Option<String> {
+    match (captured.is_empty(), stdout) {
+        (true, stdout) => stdout,
+        (false, None) => Some(captured),
+        (false, Some(stdout)) => Some(captured + &stdout),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use serde_json;
 
-    use super::{parse_test_results, Event, EventKind};
+    use crate::errors::SuityError;
+    use super::{parse_test_results, Event, EventKind, Parser};
+
+    fn parse_lenient(stdout: &str) -> (Vec<Event>, Parser) {
+        let mut parser = Parser::new(true);
+        let events = stdout.lines().filter_map(|line| parser.push(line).unwrap()).collect();
+        (events, parser)
+    }
 
     #[test]
     fn suite_started() {
@@ -215,6 +375,16 @@ mod tests {
         assert_eq!(expected, event);
     }
 
+    #[test]
+    fn test_timeout_warning() {
+        let stdout = r#"{ "type": "test", "event": "timeout", "name": "slow" }"#;
+
+        let expected = Event::new_test(EventKind::Timeout, String::from("slow"));
+
+        let event: Event = serde_json::from_str(stdout).expect("Failed to parse stdout!");
+        assert_eq!(expected, event);
+    }
+
     #[test]
     fn test_simple_output() {
         let stdout = r#"{ "type": "suite", "event": "started", "test_count": 1 }
@@ -222,17 +392,16 @@ mod tests {
 { "type": "test", "name": "parsers::test::test_zpools_on_single_zpool", "event": "ok" }
 { "type": "suite", "event": "ok", "passed": 1, "failed": 0, "allowed_fail": 0, "ignored": 0, "measured": 0, "filtered_out": 40 }"#;
 
-        let mut expected = Vec::with_capacity(4);
-        expected.push(Event::new_suite(EventKind::Started).set_test_count(1));
-        expected.push(Event::new_test(
-            EventKind::Started,
-            String::from("parsers::test::test_zpools_on_single_zpool"),
-        ));
-        expected.push(Event::new_test(
-            EventKind::Ok,
-            String::from("parsers::test::test_zpools_on_single_zpool"),
-        ));
-        expected.push(
+        let expected = vec![
+            Event::new_suite(EventKind::Started).set_test_count(1),
+            Event::new_test(
+                EventKind::Started,
+                String::from("parsers::test::test_zpools_on_single_zpool"),
+            ),
+            Event::new_test(
+                EventKind::Ok,
+                String::from("parsers::test::test_zpools_on_single_zpool"),
+            ),
             Event::new_suite(EventKind::Ok)
                 .set_passed(1)
                 .set_failed(0)
@@ -240,9 +409,111 @@ mod tests {
                 .set_ignored(0)
                 .set_measured(0)
                 .set_filtered_out(40),
-        );
+        ];
 
-        let actual = parse_test_results(stdout);
+        let actual = parse_test_results(stdout).unwrap();
         assert_eq!(expected, actual);
     }
+
+    #[test]
+    fn test_ignored_with_reason() {
+        let stdout = r#"{ "type": "test", "name": "needs_zfs", "event": "ignored", "message": "requires root" }"#;
+
+        let expected = Event::new_test(EventKind::Ignored, String::from("needs_zfs"))
+            .set_message(String::from("requires root"));
+
+        let event: Event = serde_json::from_str(stdout).expect("Failed to parse stdout!");
+        assert_eq!(expected, event);
+    }
+
+    #[test]
+    fn test_exec_time() {
+        let stdout = r#"{ "type": "test", "name": "slow", "event": "ok", "exec_time": 1.5 }
+{ "type": "test", "name": "old_slow", "event": "ok", "exec_time": "0.250s" }
+{ "type": "suite", "event": "ok", "passed": 2, "failed": 0, "allowed_fail": 0, "ignored": 0, "measured": 0, "filtered_out": 0, "exec_time": 1.75 }"#;
+
+        let expected = vec![
+            Event::new_test(EventKind::Ok, String::from("slow")).set_exec_time(1.5),
+            Event::new_test(EventKind::Ok, String::from("old_slow")).set_exec_time(0.25),
+            Event::new_suite(EventKind::Ok)
+                .set_passed(2)
+                .set_failed(0)
+                .set_allowed_fail(0)
+                .set_ignored(0)
+                .set_measured(0)
+                .set_filtered_out(0)
+                .set_exec_time(1.75),
+        ];
+
+        assert_eq!(expected, parse_test_results(stdout).unwrap());
+    }
+
+    #[test]
+    fn test_malformed_output() {
+        let stdout = r#"{ "type": "suite", "event": "started", "test_count": 1 }
+{ "type": "test", "event": "started", "name": "noisy" }
+hello from noisy
+{ "type": "test", "name": "noisy", "event": "ok" }"#;
+
+        let err = parse_test_results(stdout).unwrap_err();
+        match err {
+            SuityError::MalformedTestOutput { line, text } => {
+                assert_eq!(3, line);
+                assert_eq!("hello from noisy", text);
+            }
+            _ => panic!("unexpected error: {}", err),
+        }
+    }
+
+    #[test]
+    fn test_lenient_output() {
+        let stdout = r#"warming up
+{ "type": "suite", "event": "started", "test_count": 2 }
+{ "type": "test", "event": "started", "name": "noisy" }
+hello from noisy
+{ "type": "test", "name": "noisy", "event": "ok" }
+{ "type": "test", "event": "started", "name": "failed" }
+{ "type": "test", "name": "failed", "event": "failed", "stdout": "idk dawg" }
+{ "type": "suite", "event": "failed", "passed": 1, "failed": 1, "allowed_fail": 0, "ignored": 0, "measured": 0, "filtered_out": 0 }"#;
+
+        let expected = vec![
+            Event::new_suite(EventKind::Started).set_test_count(2),
+            Event::new_test(EventKind::Started, String::from("noisy")),
+            Event::new_test(EventKind::Ok, String::from("noisy"))
+                .set_stdout(String::from("hello from noisy\n")),
+            Event::new_test(EventKind::Started, String::from("failed")),
+            Event::new_test(EventKind::Failed, String::from("failed"))
+                .set_stdout(String::from("idk dawg")),
+            Event::new_suite(EventKind::Failed)
+                .set_passed(1)
+                .set_failed(1)
+                .set_allowed_fail(0)
+                .set_ignored(0)
+                .set_measured(0)
+                .set_filtered_out(0)
+                .set_stdout(String::from("warming up\n")),
+        ];
+
+        assert_eq!(expected, parse_lenient(stdout).0);
+    }
+
+    #[test]
+    fn test_lenient_output_of_slow_test() {
+        let stdout = r#"{ "type": "test", "event": "started", "name": "slow" }
+still working
+{ "type": "test", "event": "timeout", "name": "slow" }
+almost done
+{ "type": "test", "name": "slow", "event": "ok" }"#;
+
+        let (events, parser) = parse_lenient(stdout);
+
+        let expected = vec![
+            Event::new_test(EventKind::Started, String::from("slow")),
+            Event::new_test(EventKind::Timeout, String::from("slow")),
+            Event::new_test(EventKind::Ok, String::from("slow"))
+                .set_stdout(String::from("still working\nalmost done\n")),
+        ];
+        assert_eq!(expected, events);
+        assert_eq!(None, parser.finish());
+    }
 }
@@ -0,0 +1,310 @@
+//! Execution of test processes with time limits.
+
+use crate::errors::SuityError;
+use crate::results::{Event, EventKind, Parser};
+use std::io::{self, BufRead, BufReader, Read};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Time limits for a single test process.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct Limits {
+    /// How long whole process is allowed to run.
+    pub suite: Option<Duration>,
+    /// How long each test is allowed to run, measured from its `started` event.
+    pub test: Option<Duration>,
+}
+
+/// How long to wait for output that was still in flight when process got killed.
+const TRAILING_OUTPUT_GRACE: Duration = Duration::from_secs(1);
+
+/// Outcome of test process.
+#[derive(Debug)]
+pub struct Execution {
+    pub status: ExitStatus,
+    pub stderr: String,
+    /// Set if process was killed for exceeding one of the limits.
+    pub timed_out: Option<Timeout>,
+}
+
+/// Limit that got test process killed.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Timeout {
+    /// Human readable reason, e.g. "test hangs timed out after 60s"
+    pub reason: String,
+    /// Test that ran out of time, `None` if the whole process did.
+    pub test: Option<String>,
+}
+
+/// Run command to completion or until one of the limits is hit, whichever comes first. Stdout is
+/// fed through `parser` line by line and every event is handed to `on_event` as soon as it
+/// arrives. When a limit is hit, the whole process group is killed and output it managed to write
+/// before that is still collected. If either `parser` or
+/// `on_event` fails, the process is killed and the error is returned.
+pub fn run<F>(
+    mut command: Command,
+    limits: &Limits,
+    parser: &mut Parser,
+    mut on_event: F,
+) -> Result<Execution, SuityError>
+where
+    F: FnMut(Event) -> Result<(), SuityError>,
+{
+    command.stdout(Stdio::piped()).stderr(Stdio::piped());
+    // Own process group makes it possible to kill everything test spawned, but also detaches it
+    // from terminal's Ctrl-C. Only do that when there is a limit to enforce.
+    let own_group = limits.suite.is_some() || limits.test.is_some();
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        if own_group {
+            command.process_group(0);
+        }
+    }
+    let mut child = command.spawn()?;
+    let stdout = child.stdout.take().expect("stdout is piped");
+    let mut stderr = child.stderr.take().expect("stderr is piped");
+
+    let (tx, rx) = mpsc::channel();
+    let stdout_reader = thread::spawn(move || {
+        for line in BufReader::new(stdout).split(b'\n') {
+            if tx.send(line).is_err() {
+                break;
+            }
+        }
+    });
+    let stderr_buf = Arc::new(Mutex::new(Vec::new()));
+    // Dropped once stderr is closed, so it is possible to wait for that with a timeout.
+    let (stderr_done, stderr_closed) = mpsc::channel::<()>();
+    let stderr_reader = {
+        let stderr_buf = stderr_buf.clone();
+        thread::spawn(move || {
+            let _done = stderr_done;
+            let mut chunk = [0; 4096];
+            while let Ok(n) = stderr.read(&mut chunk) {
+                if n == 0 {
+                    break;
+                }
+                stderr_buf.lock().unwrap().extend_from_slice(&chunk[..n]);
+            }
+        })
+    };
+
+    let started_at = Instant::now();
+    let mut running: Vec<(String, Instant)> = Vec::new();
+    let mut timed_out = None;
+    let mut killed_at = None;
+    let mut stdout_closed = false;
+
+    loop {
+        let deadline = match killed_at {
+            Some(killed_at) => Some(killed_at + TRAILING_OUTPUT_GRACE),
+            None => next_deadline(started_at, &running, limits).map(|(deadline, _)| deadline),
+        };
+        let received = match deadline {
+            Some(deadline) => {
+                let now = Instant::now();
+                if deadline <= now {
+                    Err(RecvTimeoutError::Timeout)
+                } else {
+                    rx.recv_timeout(deadline - now)
+                }
+            }
+            None => rx.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+        match received {
+            Ok(Ok(line)) => {
+                let line = String::from_utf8_lossy(&line);
+                let handled = parser.push(&line).and_then(|event| match event {
+                    Some(event) => {
+                        track_running(&event, &mut running);
+                        on_event(event)
+                    }
+                    None => Ok(()),
+                });
+                if let Err(e) = handled {
+                    kill(&mut child, own_group)?;
+                    child.wait()?;
+                    return Err(e);
+                }
+            }
+            Ok(Err(_)) | Err(RecvTimeoutError::Disconnected) => {
+                stdout_closed = true;
+                break;
+            }
+            Err(RecvTimeoutError::Timeout) if killed_at.is_some() => break,
+            Err(RecvTimeoutError::Timeout) => {
+                let (_, timeout) = next_deadline(started_at, &running, limits)
+                    .expect("timed out without a deadline");
+                timed_out = Some(timeout);
+                kill(&mut child, own_group)?;
+                killed_at = Some(Instant::now());
+            }
+        }
+    }
+
+    let status = child.wait()?;
+    match killed_at {
+        None => {
+            let _ = stdout_reader.join();
+            let _ = stderr_reader.join();
+        }
+        Some(killed_at) => {
+            // Killed process could leave behind descendants holding the pipes open, only wait for
+            // them until grace period is over.
+            if stdout_closed {
+                let _ = stdout_reader.join();
+            }
+            let remaining = (killed_at + TRAILING_OUTPUT_GRACE).saturating_duration_since(Instant::now());
+            if let Err(RecvTimeoutError::Disconnected) = stderr_closed.recv_timeout(remaining) {
+                let _ = stderr_reader.join();
+            }
+        }
+    }
+    let stderr = String::from_utf8_lossy(&stderr_buf.lock().unwrap()).into();
+
+    Ok(Execution {
+        status,
+        stderr,
+        timed_out,
+    })
+}
+
+/// Earliest point in time when one of the limits is hit and why.
+fn next_deadline(
+    started_at: Instant,
+    running: &[(String, Instant)],
+    limits: &Limits,
+) -> Option<(Instant, Timeout)> {
+    let suite = limits.suite.map(|limit| {
+        let timeout = Timeout {
+            reason: format!("timed out after {}s", limit.as_secs_f64()),
+            test: None,
+        };
+        (started_at + limit, timeout)
+    });
+    let test = limits.test.and_then(|limit| {
+        running
+            .iter()
+            .min_by_key(|(_, started)| *started)
+            .map(|(name, started)| {
+                let timeout = Timeout {
+                    reason: format!("test {} timed out after {}s", name, limit.as_secs_f64()),
+                    test: Some(name.clone()),
+                };
+                (*started + limit, timeout)
+            })
+    });
+    match (suite, test) {
+        (Some(suite), Some(test)) => Some(if test.0 < suite.0 { test } else { suite }),
+        (suite, test) => suite.or(test),
+    }
+}
+
+fn track_running(event: &Event, running: &mut Vec<(String, Instant)>) {
+    if let Event::Test(t) = event {
+        // Slow test warning, the test is still running.
+        if t.event == EventKind::Timeout {
+            return;
+        }
+        running.retain(|(name, _)| name != &t.name);
+        if t.event == EventKind::Started {
+            running.push((t.name.clone(), Instant::now()));
+        }
+    }
+}
+
+#[cfg(unix)]
+fn kill(child: &mut Child, own_group: bool) -> io::Result<()> {
+    // Child is the leader of its own process group, take down everything it has spawned.
+    let pgid = child.id() as libc::pid_t;
+    if own_group && unsafe { libc::kill(-pgid, libc::SIGKILL) } == 0 {
+        Ok(())
+    } else {
+        child.kill()
+    }
+}
+
+#[cfg(not(unix))]
+fn kill(child: &mut Child, _own_group: bool) -> io::Result<()> {
+    child.kill()
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::{run, Execution, Limits, Timeout};
+    use crate::errors::SuityError;
+    use crate::results::{Event, EventKind, Parser};
+    use std::process::Command;
+    use std::time::Duration;
+
+    fn run_script(script: &str, limits: &Limits, lenient: bool) -> Result<(Execution, Vec<Event>), SuityError> {
+        let mut command = Command::new("sh");
+        command.arg("-c").arg(script);
+        let mut events = Vec::new();
+        let execution = run(command, limits, &mut Parser::new(lenient), |event| {
+            events.push(event);
+            Ok(())
+        })?;
+        Ok((execution, events))
+    }
+
+    #[test]
+    fn run_to_completion() {
+        let script = r#"echo '{ "type": "suite", "event": "started", "test_count": 0 }'; echo oops >&2"#;
+        let (execution, events) = run_script(script, &Limits::default(), false).unwrap();
+
+        assert!(execution.status.success());
+        assert!(execution.timed_out.is_none());
+        assert_eq!(vec![Event::new_suite(EventKind::Started).set_test_count(0)], events);
+        assert_eq!("oops\n", execution.stderr);
+    }
+
+    #[test]
+    fn kill_on_malformed_output() {
+        let script = "echo garbage; sleep 30";
+        let result = run_script(script, &Limits::default(), false);
+
+        assert!(matches!(result, Err(SuityError::MalformedTestOutput { line: 1, .. })));
+    }
+
+    #[test]
+    fn kill_on_test_timeout() {
+        let script = r#"echo '{ "type": "test", "event": "started", "name": "hangs" }'; sleep 0.1
+echo '{ "type": "test", "event": "started", "name": "innocent" }'; echo oops >&2; sleep 30"#;
+        let limits = Limits {
+            suite: Some(Duration::from_secs(20)),
+            test: Some(Duration::from_millis(200)),
+        };
+        let (execution, events) = run_script(script, &limits, true).unwrap();
+
+        assert!(!execution.status.success());
+        let expected = Timeout {
+            reason: String::from("test hangs timed out after 0.2s"),
+            test: Some(String::from("hangs")),
+        };
+        assert_eq!(Some(expected), execution.timed_out);
+        assert_eq!(Event::new_test(EventKind::Started, String::from("hangs")), events[0]);
+        assert_eq!(2, events.len());
+        assert_eq!("oops\n", execution.stderr);
+    }
+
+    #[test]
+    fn kill_on_suite_timeout() {
+        let limits = Limits {
+            suite: Some(Duration::from_millis(200)),
+            test: None,
+        };
+        let (execution, _) = run_script("sleep 30", &limits, true).unwrap();
+
+        assert!(!execution.status.success());
+        let expected = Timeout {
+            reason: String::from("timed out after 0.2s"),
+            test: None,
+        };
+        assert_eq!(Some(expected), execution.timed_out);
+    }
+}
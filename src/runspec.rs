@@ -1,38 +1,200 @@
+use crate::allure::AllureReporter;
+use crate::artifacts::{self, Artifact};
+use crate::ctrf::CtrfReporter;
 use crate::errors::SuityError;
-use crate::junit::TestSuite;
-use std::fs;
+use crate::junit::{Abort, JUnitReporter, TestSuite, TestSuiteBuilder};
+use crate::listener::Listener;
+use crate::manifest::Manifest;
+use crate::metadata::{Metadata, Package};
+use crate::github::GitHubReporter;
+use crate::html::HtmlReporter;
+use crate::json::JsonReporter;
+use crate::markdown::MarkdownReporter;
+use crate::nunit::NUnit3Reporter;
+use crate::report::{Reporter, RunReporter};
+use crate::tap::TapReporter;
+use crate::teamcity::TeamCity;
+use crate::trx::TrxReporter;
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus, Stdio};
 use std::io;
-use std::path::PathBuf;
-use std::process::{Command, Output};
+use std::str::FromStr;
+use itertools::Itertools;
 
-use crate::results;
+use crate::results::Parser;
+use crate::runner::{self, Limits};
+use std::time::{Duration, SystemTime};
 
+/// Outcome of a single workflow.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum RunspecResult {
     Ok,
+    /// Number of tests that failed or erred out.
     Errors(u64),
+    /// Tests didn't compile.
+    BuildFailed,
 }
 
-impl RunspecResult {
-    pub fn as_exit_code(&self) -> i32 {
+/// Exit code used by suity itself when it fails to run tests at all.
+pub const INTERNAL_ERROR_EXIT_CODE: i32 = 101;
+
+/// Exit code of `Count` policy when tests of some workflow didn't compile. Above any count of
+/// failed tests and distinct from internal error.
+pub const COUNT_BUILD_FAILED_EXIT_CODE: i32 = 102;
+
+/// How outcome of all workflows is turned into process exit code.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExitCodePolicy {
+    /// 0 - everything passed, 1 - some tests failed, 2 - some tests didn't compile.
+    #[default]
+    Status,
+    /// Number of failed tests, capped at 100 so it never wraps or collides with internal error.
+    /// 102 if some tests didn't compile.
+    Count,
+}
+
+impl ExitCodePolicy {
+    pub fn exit_code(&self, results: &[RunspecResult]) -> i32 {
+        let build_failed = results.contains(&RunspecResult::BuildFailed);
+        let failed: u64 = results
+            .iter()
+            .map(|r| match r {
+                RunspecResult::Errors(n) => *n,
+                _ => 0,
+            })
+            .fold(0, u64::saturating_add);
         match self {
-            RunspecResult::Ok => 0,
-            RunspecResult::Errors(n) => *n as i32,
+            ExitCodePolicy::Status if build_failed => 2,
+            ExitCodePolicy::Status if failed > 0 => 1,
+            ExitCodePolicy::Status => 0,
+            ExitCodePolicy::Count if build_failed => COUNT_BUILD_FAILED_EXIT_CODE,
+            ExitCodePolicy::Count => failed.min(100) as i32,
         }
     }
 }
-/// Desired output format. Right not only JUnit is supported and it's the default format.
-#[derive(Debug, Copy, Clone, Deserialize)]
+
+impl FromStr for ExitCodePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<ExitCodePolicy, String> {
+        match s.to_lowercase().as_str() {
+            "status" => Ok(ExitCodePolicy::Status),
+            "count" => Ok(ExitCodePolicy::Count),
+            _ => Err(format!("Unknown exit code policy: {}", s)),
+        }
+    }
+}
+/// Desired output format. JUnit is the default format.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Deserialize)]
 pub enum OutputFormat {
+    #[default]
     JUnit,
+    Tap,
+    Json,
+    Html,
+    Markdown,
+    GitHub,
+    TeamCity,
+    Trx,
+    NUnit3,
+    Ctrf,
+    Allure,
 }
 
-impl Default for OutputFormat {
-    fn default() -> OutputFormat {
-        OutputFormat::JUnit
+impl OutputFormat {
+    /// Reporter writing this format once a workflow is complete, `None` for formats that are
+    /// streamed while tests run or cover a whole run.
+    pub fn reporter(&self) -> Option<Box<dyn Reporter>> {
+        let reporter: Box<dyn Reporter> = match self {
+            OutputFormat::JUnit => Box::new(JUnitReporter),
+            OutputFormat::Tap => Box::new(TapReporter),
+            OutputFormat::Json => Box::new(JsonReporter),
+            OutputFormat::GitHub => Box::new(GitHubReporter),
+            OutputFormat::Html | OutputFormat::Markdown | OutputFormat::TeamCity => return None,
+            OutputFormat::Trx => Box::new(TrxReporter),
+            OutputFormat::NUnit3 => Box::new(NUnit3Reporter),
+            OutputFormat::Ctrf => Box::new(CtrfReporter),
+            OutputFormat::Allure => Box::new(AllureReporter),
+        };
+        Some(reporter)
+    }
+
+    /// Reporter writing this format once the whole run is complete, `None` for formats written
+    /// per workflow.
+    pub fn run_reporter(&self) -> Option<Box<dyn RunReporter>> {
+        match self {
+            OutputFormat::Html => Some(Box::new(HtmlReporter)),
+            OutputFormat::Markdown => Some(Box::new(MarkdownReporter)),
+            _ => None,
+        }
+    }
+
+    /// Listener streaming this format for `suites` while tests run, `None` for formats that are
+    /// written afterwards.
+    pub fn listener(&self, suites: HashSet<String>) -> Option<Box<dyn Listener + Send>> {
+        match self {
+            OutputFormat::TeamCity => Some(Box::new(TeamCity::new(io::stdout(), suites))),
+            _ => None,
+        }
     }
 }
 
-#[derive(Debug, Deserialize)]
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<OutputFormat, String> {
+        match s.to_lowercase().as_str() {
+            "junit" => Ok(OutputFormat::JUnit),
+            "tap" => Ok(OutputFormat::Tap),
+            "json" => Ok(OutputFormat::Json),
+            "html" => Ok(OutputFormat::Html),
+            "markdown" | "md" => Ok(OutputFormat::Markdown),
+            "github" => Ok(OutputFormat::GitHub),
+            "teamcity" => Ok(OutputFormat::TeamCity),
+            "trx" => Ok(OutputFormat::Trx),
+            "nunit3" | "nunit" => Ok(OutputFormat::NUnit3),
+            "ctrf" => Ok(OutputFormat::Ctrf),
+            "allure" => Ok(OutputFormat::Allure),
+            _ => Err(format!("Unknown output format: {}", s)),
+        }
+    }
+}
+
+/// Feature combinations a workflow is tested with, each one on top of workflow's own features.
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum FeatureMatrix {
+    /// Explicit list of combinations.
+    Combinations(Vec<Vec<String>>),
+    /// Every combination of up to `depth` features declared in `[features]` of Cargo.toml,
+    /// except `exclude`d ones.
+    Powerset {
+        #[serde(default = "default::matrix_depth")]
+        depth: usize,
+        #[serde(default)]
+        exclude: Vec<String>,
+    },
+}
+
+impl FeatureMatrix {
+    /// Combinations to test. `declared` are features of the crate, only used by `Powerset`.
+    pub fn combinations(&self, declared: &[String]) -> Vec<Vec<String>> {
+        match self {
+            FeatureMatrix::Combinations(combinations) => combinations.clone(),
+            FeatureMatrix::Powerset { depth, exclude } => {
+                let features: Vec<&String> = declared.iter().filter(|f| !exclude.contains(f)).collect();
+                (0..=(*depth).min(features.len()))
+                    .flat_map(|size| features.iter().cloned().cloned().combinations(size))
+                    .collect()
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct Runspec {
     /// How to name this spec.
     #[serde(default = "default::name")]
@@ -40,9 +202,24 @@ pub struct Runspec {
     /// List of features to pass to cargo.
     #[serde(default = "default::features")]
     pub features: Vec<String>,
-    /// Report format.
-    #[serde(default = "default::format")]
-    pub format: OutputFormat,
+    /// Build with crate's default features. Default true.
+    #[serde(default = "default::default_features")]
+    pub default_features: bool,
+    /// Build with all features of the crate. Default false.
+    #[serde(default)]
+    pub all_features: bool,
+    /// Turn this spec into one spec per feature combination. Default none.
+    #[serde(default)]
+    pub feature_matrix: Option<FeatureMatrix>,
+    /// Members of the workspace to test. Default none, package in current directory is tested.
+    #[serde(default)]
+    pub packages: Vec<String>,
+    /// Test every member of the workspace, `packages` is ignored. Default false.
+    #[serde(default)]
+    pub workspace: bool,
+    /// Report formats, every one of them is written to `output`.
+    #[serde(default = "default::format", deserialize_with = "formats::deserialize")]
+    pub format: Vec<OutputFormat>,
     /// Output directory. Default `./test-results/`
     #[serde(default = "default::output")]
     pub output: PathBuf,
@@ -55,6 +232,24 @@ pub struct Runspec {
     /// List of integration tests to run. Default all of them.
     #[serde(default = "default::integration")]
     pub integration: Vec<String>,
+    /// Binaries to run unit tests of, `true` for all of them. Default none.
+    #[serde(default, deserialize_with = "targets::deserialize")]
+    pub bins: Vec<String>,
+    /// Examples to run tests of, `true` for all of them. Default none.
+    #[serde(default, deserialize_with = "targets::deserialize")]
+    pub examples: Vec<String>,
+    /// Benches to run in test mode, `true` for all of them. Default none.
+    #[serde(default, deserialize_with = "targets::deserialize")]
+    pub benches: Vec<String>,
+    /// Attach unparseable test output to tests instead of failing. Default true.
+    #[serde(default = "default::lenient")]
+    pub lenient: bool,
+    /// Time limit in seconds for each test suite. Default none.
+    #[serde(default)]
+    pub timeout: Option<u64>,
+    /// Time limit in seconds for each test. Default none.
+    #[serde(default)]
+    pub test_timeout: Option<u64>,
 }
 
 impl Default for Runspec {
@@ -62,11 +257,22 @@ impl Default for Runspec {
         Runspec {
             name: String::from("default"),
             features: Vec::new(),
-            format: OutputFormat::default(),
+            default_features: true,
+            all_features: false,
+            feature_matrix: None,
+            packages: Vec::new(),
+            workspace: false,
+            format: vec![OutputFormat::default()],
             output: PathBuf::from("test-results/"),
             doc: true,
             lib: true,
             integration: vec![String::from("*")],
+            bins: Vec::new(),
+            examples: Vec::new(),
+            benches: Vec::new(),
+            lenient: true,
+            timeout: None,
+            test_timeout: None,
         }
     }
 }
@@ -76,132 +282,411 @@ impl Runspec {
         itertools::join(self.features.iter(), " ")
     }
 
-    pub fn execute<W: io::Write>(&mut self, output: W) -> Result<Vec<TestSuite>, SuityError> {
-        let mut results: Vec<TestSuite> = Vec::with_capacity(5);
-        let shared_args = self.get_shared_args();
+    /// Turn spec with `feature_matrix` into a spec per feature combination, named like
+    /// `default[+serde-json]`. Combination without extra features keeps name of the spec. Features
+    /// of `Powerset` are read from `manifest`, or from `cargo metadata` when spec selects
+    /// workspace members.
+    pub fn expand_feature_matrix(mut self, manifest: &Path) -> Result<Vec<Runspec>, SuityError> {
+        let matrix = match self.feature_matrix.take() {
+            Some(matrix) => matrix,
+            None => return Ok(vec![self]),
+        };
+        let declared = match matrix {
+            FeatureMatrix::Combinations(_) => Vec::new(),
+            FeatureMatrix::Powerset { .. } if self.packages.is_empty() && !self.workspace => {
+                Manifest::from_path(manifest)?.feature_names()
+            }
+            FeatureMatrix::Powerset { .. } => shared_features(&self.selected_packages()?),
+        };
+        let specs = matrix
+            .combinations(&declared)
+            .into_iter()
+            .map(|combination| {
+                let mut spec = self.clone();
+                if !combination.is_empty() {
+                    let added: String = combination.iter().map(|f| format!("+{}", f)).collect();
+                    spec.name = format!("{}[{}]", self.name, added);
+                    spec.features.extend(combination);
+                }
+                spec
+            })
+            .collect();
+        Ok(specs)
+    }
 
-        let mut args = shared_args.clone();
+    /// Build tests of this spec and return test processes that need to run, in report order.
+    pub fn prepare(&self) -> Result<Vec<Job>, SuityError> {
+        let packages = self.selected_packages()?;
+        let mut args = self.get_shared_args();
+        if self.workspace {
+            args.push(String::from("--workspace"));
+        } else {
+            for package in &packages {
+                args.push(String::from("-p"));
+                args.push(package.name.clone());
+            }
+        }
+        let mut artifacts = self.build(args.clone())?;
+        // Selecting any target makes cargo skip default ones, so extra targets are a separate build.
+        let extra = self.extra_target_args();
+        if !extra.is_empty() {
+            args.extend(extra);
+            artifacts.extend(self.build(args)?);
+        }
+        self.jobs(&packages, &artifacts)
+    }
+
+    /// Build tests without running them and return what was built.
+    fn build(&self, mut args: Vec<String>) -> Result<Vec<Artifact>, SuityError> {
         args.push(String::from("--no-run"));
-        let status = Command::new("cargo").args(args).status()?;
-        if !status.success() {
+        args.push(String::from("--message-format=json-render-diagnostics"));
+        let out = Command::new("cargo")
+            .args(args)
+            .stderr(Stdio::inherit())
+            .output()?;
+        if !out.status.success() {
             return Err(SuityError::FailedToCompile {
                 workflow: self.name.clone(),
             });
         }
-        if self.lib {
+        let stdout: String = String::from_utf8_lossy(&out.stdout).into();
+        Ok(artifacts::parse_artifacts(&stdout))
+    }
+
+    /// Targets tested by running their own executable.
+    fn executable_targets(&self) -> [TargetSelection<'_>; 4] {
+        [
+            TargetSelection { kind: "test", flags: None, names: &self.integration },
+            TargetSelection { kind: "bin", flags: Some(("--bin", "--bins")), names: &self.bins },
+            TargetSelection { kind: "example", flags: Some(("--example", "--examples")), names: &self.examples },
+            TargetSelection { kind: "bench", flags: Some(("--bench", "--benches")), names: &self.benches },
+        ]
+    }
+
+    /// Arguments to build selected binaries, examples and benches with test harness.
+    fn extra_target_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        for target in &self.executable_targets() {
+            let (one, all) = match target.flags {
+                Some(flags) => flags,
+                None => continue,
+            };
+            if target.names.iter().any(|name| name == "*") {
+                args.push(String::from(all));
+                continue;
+            }
+            for name in target.names {
+                args.push(String::from(one));
+                args.push(name.clone());
+            }
+        }
+        args
+    }
+
+    /// Members of the workspace selected by `packages` or `workspace`. Empty when spec tests
+    /// package in current directory.
+    fn selected_packages(&self) -> Result<Vec<Package>, SuityError> {
+        if self.packages.is_empty() && !self.workspace {
+            return Ok(Vec::new());
+        }
+        let metadata = Metadata::load()?;
+        if self.workspace {
+            return Ok(metadata.packages);
+        }
+        self.packages
+            .iter()
+            .map(|name| {
+                metadata.package(name).cloned().ok_or_else(|| SuityError::PackageNotFound {
+                    name: name.clone(),
+                    workflow: self.name.clone(),
+                })
+            })
+            .collect()
+    }
+
+    /// Test processes for built `artifacts`, package after package.
+    fn jobs(&self, packages: &[Package], artifacts: &[Artifact]) -> Result<Vec<Job>, SuityError> {
+        let mut jobs: Vec<Job> = Vec::with_capacity(5);
+        if packages.is_empty() {
+            self.package_jobs(None, artifacts, &mut jobs)?;
+            return Ok(jobs);
+        }
+        for package in packages {
+            self.package_jobs(Some(package), artifacts, &mut jobs)?;
+        }
+        // Listed target only has to exist in one of the packages.
+        for TargetSelection { kind, names, .. } in self.executable_targets() {
+            let missing = names
+                .iter()
+                .find(|name| *name != "*" && !packages.iter().any(|package| package.has_target(kind, name)));
+            if let Some(name) = missing {
+                return Err(SuityError::TestBinaryNotFound {
+                    name: name.clone(),
+                    workflow: self.name.clone(),
+                });
+            }
+        }
+        Ok(jobs)
+    }
+
+    /// Jobs of a single package. Suites of a workspace member are prefixed with its name, e.g.
+    /// `[default] core::Lib-tests`.
+    fn package_jobs(&self, package: Option<&Package>, artifacts: &[Artifact], jobs: &mut Vec<Job>) -> Result<(), SuityError> {
+        let prefix = package.map(|package| format!("{}::", package.name)).unwrap_or_default();
+        let mut shared_args = self.get_shared_args();
+        if let Some(package) = package {
+            shared_args.push(String::from("-p"));
+            shared_args.push(package.name.clone());
+        }
+        let has_lib = package.map_or(true, Package::has_lib);
+        if self.lib && has_lib {
             let mut args = shared_args.clone();
             args.push(String::from("--lib"));
             args.push(String::from("--"));
             add_common_args(&mut args);
 
-            let test_suite_name = format!("[{}] Lib-tests", self.name).to_string();
+            let test_suite_name = format!("[{}] {}Lib-tests", self.name, prefix);
 
-            if let Some(suite) = Runspec::run_cargo(&mut args, test_suite_name)? {
-                results.push(suite);
-            }
+            jobs.push(self.cargo_job(&args, test_suite_name));
         }
-        if self.doc {
+        if self.doc && has_lib {
             let mut args = shared_args.clone();
             args.push(String::from("--doc"));
             args.push(String::from("--"));
             add_common_args(&mut args);
-            let test_suite_name = format!("[{}] Doc-tests", self.name).to_string();
+            let test_suite_name = format!("[{}] {}Doc-tests", self.name, prefix);
 
-            if let Some(suite) = Runspec::run_cargo(&mut args, test_suite_name)? {
-                results.push(suite);
-            }
+            jobs.push(self.cargo_job(&args, test_suite_name));
         }
 
-        if !self.integration.is_empty() {
-            let tests = {
-                if self.integration == vec!["*"] {
-                    get_integration_tests()
+        for TargetSelection { kind, names: selection, .. } in self.executable_targets() {
+            if selection.is_empty() {
+                continue;
+            }
+            let binaries = artifacts::test_executables(
+                artifacts
+                    .iter()
+                    .filter(|artifact| package.map_or(true, |package| artifact.package_id == package.id)),
+                kind,
+            );
+            let names: Vec<String> = {
+                if selection == ["*"] {
+                    binaries.keys().cloned().collect()
                 } else {
-                    self.integration.clone()
+                    selection
+                        .iter()
+                        .filter(|name| package.map_or(true, |package| package.has_target(kind, name)))
+                        .cloned()
+                        .collect()
                 }
             };
-            for name in tests {
+            // Integration tests are named after their file, other targets get their kind too.
+            let prefix = if kind == "test" {
+                prefix.clone()
+            } else {
+                format!("{}{}::", prefix, kind)
+            };
+            for name in names {
                 if name != "*" {
-                    if let Some(suite) = self.run_integration_test(&name)? {
-                        results.push(suite);
-                    }
+                    jobs.push(self.test_binary_job(&prefix, &name, &binaries)?);
                 }
             }
         }
-        crate::junit::write_as_xml(&results, output)?;
-        Ok(results)
+        Ok(())
     }
 
-    fn run_integration_test(&mut self, test: &String) -> Result<Option<TestSuite>, SuityError> {
-        let test_suite_name = format!("[{}] {}", self.name, &test).to_string();
-        if let Some(path) = map_to_binary(&test) {
+    /// Write report in every format of this spec for suites produced by it.
+    pub fn write_reports(&self, results: &[TestSuite]) -> Result<(), SuityError> {
+        for reporter in self.format.iter().filter_map(OutputFormat::reporter) {
+            reporter.report(self, results)?;
+        }
+        Ok(())
+    }
+
+    /// Listeners for every streamed format of this spec, following suites of `jobs` only.
+    pub fn listeners(&self, jobs: &[Job]) -> Vec<Box<dyn Listener + Send>> {
+        let suites: HashSet<String> = jobs.iter().map(|job| job.suite.clone()).collect();
+        self.format
+            .iter()
+            .filter_map(|format| format.listener(suites.clone()))
+            .collect()
+    }
+
+    fn test_binary_job(
+        &self,
+        prefix: &str,
+        test: &str,
+        binaries: &BTreeMap<String, PathBuf>,
+    ) -> Result<Job, SuityError> {
+        let test_suite_name = format!("[{}] {}{}", self.name, prefix, test);
+        if let Some(path) = binaries.get(test) {
             let mut args = Vec::with_capacity(3);
             add_common_args(&mut args);
-            let out = Command::new(path).args(&args).output()?;
-            Runspec::parse_test_output(test_suite_name, &out)
+            let mut command = Command::new(path);
+            command.args(&args);
+            Ok(self.job(command, test_suite_name))
         } else {
             Err(SuityError::TestBinaryNotFound {
-                name: test.clone(),
+                name: test.to_string(),
                 workflow: self.name.clone(),
             })
         }
     }
 
-    fn run_cargo(
-        args: &Vec<String>,
-        test_suite_name: String,
-    ) -> Result<Option<TestSuite>, SuityError> {
-        let out = Command::new("cargo").args(args).output()?;
-        Runspec::parse_test_output(test_suite_name, &out)
+    fn cargo_job(&self, args: &[String], test_suite_name: String) -> Job {
+        let mut command = Command::new("cargo");
+        command.args(args);
+        self.job(command, test_suite_name)
     }
 
-    fn parse_test_output(
-        test_suite_name: String,
-        out: &Output,
-    ) -> Result<Option<TestSuite>, SuityError> {
-        let stdout: String = String::from_utf8_lossy(&out.stdout).into();
-        let events = results::parse_test_results(&stdout);
-        let suite = TestSuite::new(events, test_suite_name)?;
-        if suite.tests > 0 {
-            Ok(Some(suite))
-        } else {
-            Ok(None)
+    fn job(&self, command: Command, suite: String) -> Job {
+        Job::new(suite, command, self.limits(), self.lenient)
+    }
+
+    fn limits(&self) -> Limits {
+        Limits {
+            suite: self.timeout.map(Duration::from_secs),
+            test: self.test_timeout.map(Duration::from_secs),
         }
     }
 
-    fn get_shared_args(&mut self) -> Vec<String> {
+    fn get_shared_args(&self) -> Vec<String> {
         let mut args: Vec<String> = vec![String::from("test")];
         if !self.features.is_empty() {
             args.push(String::from("--features"));
             args.push(self.features_to_string());
         }
+        if !self.default_features {
+            args.push(String::from("--no-default-features"));
+        }
+        if self.all_features {
+            args.push(String::from("--all-features"));
+        }
         args
     }
+}
 
-    pub fn get_output_file_path(&self) -> PathBuf {
-        let mut output_path = PathBuf::new();
-        output_path.push(&self.output);
-        output_path.push(&self.name);
-        output_path.set_extension("xml");
-        output_path
-    }
+/// Targets of one kind tested by running their own executable.
+struct TargetSelection<'a> {
+    /// Cargo's kind of the target, e.g. `bin`.
+    kind: &'static str,
+    /// Cargo flags selecting one or all targets of the kind. `None` if cargo builds them anyway.
+    flags: Option<(&'static str, &'static str)>,
+    /// Names of selected targets, `*` selects all of them.
+    names: &'a [String],
+}
+
+/// Single test process of a workflow, produces one test suite.
+#[derive(Debug)]
+pub struct Job {
+    /// Name of the suite this job produces.
+    pub suite: String,
+    command: Command,
+    limits: Limits,
+    lenient: bool,
 }
 
-fn get_integration_tests() -> Vec<String> {
-    if let Ok(entries) = fs::read_dir("tests/") {
-        entries
-            .filter_map(Result::ok)
-            .filter(filters::file_with_content)
-            .map(filters::to_path)
-            .filter(|path| filters::extension_is(path, "rs"))
-            .filter_map(|path| {
-                path.clone()
-                    .file_stem()
-                    .map(|osstr| String::from(osstr.to_string_lossy()))
+impl Job {
+    pub fn new(suite: String, command: Command, limits: Limits, lenient: bool) -> Job {
+        Job {
+            suite,
+            command,
+            limits,
+            lenient,
+        }
+    }
+
+    /// Run test process and build suite from its events as they arrive. Returns nothing if
+    /// process had no tests to run.
+    pub fn run<L: Listener>(self, listener: &mut L) -> Result<Option<TestSuite>, SuityError> {
+        let Job {
+            suite: test_suite_name,
+            command,
+            limits,
+            lenient,
+        } = self;
+        listener.suite_started(&test_suite_name);
+        let mut parser = Parser::new(lenient);
+        let mut builder = TestSuiteBuilder::new(test_suite_name.clone());
+        let started = SystemTime::now();
+        let out = runner::run(command, &limits, &mut parser, |event| {
+            listener.event(&test_suite_name, &event);
+            if let Some(test_case) = builder.push(event)? {
+                listener.test_finished(&test_suite_name, test_case);
+            }
+            Ok(())
+        })?;
+        if let Some(output) = parser.finish() {
+            builder.append_system_out(output);
+        }
+        let abort = if out.status.success() {
+            None
+        } else {
+            let (reason, culprit) = match out.timed_out {
+                Some(timeout) => (timeout.reason, timeout.test),
+                None => (describe_exit_status(&out.status), None),
+            };
+            Some(Abort {
+                reason,
+                stderr: out.stderr,
+                culprit,
             })
-            .collect()
-    } else {
-        Vec::new()
+        };
+        let mut suite = builder.finish(abort);
+        suite.timestamp = Some(started);
+        listener.suite_finished(&suite);
+        if suite.tests > 0 {
+            Ok(Some(suite))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// Features declared by every one of `packages`. Each package is tested with the same
+/// `--features`, so features only some of them declare can't be combined.
+fn shared_features(packages: &[Package]) -> Vec<String> {
+    let (first, rest) = match packages.split_first() {
+        Some(split) => split,
+        None => return Vec::new(),
+    };
+    first
+        .features
+        .keys()
+        .filter(|name| name.as_str() != "default")
+        .filter(|name| rest.iter().all(|package| package.features.contains_key(*name)))
+        .cloned()
+        .collect()
+}
+
+fn describe_exit_status(status: &ExitStatus) -> String {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        if let Some(signal) = status.signal() {
+            return match signal_name(signal) {
+                Some(name) => format!("terminated by signal {} ({})", signal, name),
+                None => format!("terminated by signal {}", signal),
+            };
+        }
+    }
+    match status.code() {
+        Some(code) => format!("exited with status {}", code),
+        None => String::from("terminated abnormally"),
+    }
+}
+
+#[cfg(unix)]
+fn signal_name(signal: i32) -> Option<&'static str> {
+    match signal {
+        4 => Some("SIGILL"),
+        6 => Some("SIGABRT"),
+        7 => Some("SIGBUS"),
+        8 => Some("SIGFPE"),
+        9 => Some("SIGKILL"),
+        11 => Some("SIGSEGV"),
+        15 => Some("SIGTERM"),
+        _ => None,
     }
 }
 
@@ -209,22 +694,7 @@ fn add_common_args(args: &mut Vec<String>) {
     args.push(String::from("-Z"));
     args.push(String::from("unstable-options"));
     args.push(String::from("--format=json"));
-}
-
-fn map_to_binary(name: &String) -> Option<PathBuf> {
-    if let Ok(entries) = fs::read_dir("target/debug/deps") {
-        let mut executables: Vec<PathBuf> = entries
-            .filter_map(Result::ok)
-            .filter(filters::file_with_content)
-            .map(filters::to_path)
-            .filter(filters::is_executable)
-            .filter(|f| filters::filename_starts_with(&f, &name))
-            .collect();
-        executables.sort_by(filters::sort_by_modify_date);
-        executables.last().cloned()
-    } else {
-        None
-    }
+    args.push(String::from("--report-time"));
 }
 
 mod default {
@@ -234,62 +704,262 @@ mod default {
     pub fn features() -> Vec<String> {
         super::Runspec::default().features.clone()
     }
-    pub fn format() -> super::OutputFormat {
-        super::Runspec::default().format.clone()
+    pub fn default_features() -> bool {
+        super::Runspec::default().default_features
+    }
+    pub fn matrix_depth() -> usize {
+        2
+    }
+    pub fn format() -> Vec<super::OutputFormat> {
+        super::Runspec::default().format
     }
     pub fn output() -> std::path::PathBuf {
         super::Runspec::default().output.clone()
     }
     pub fn doc() -> bool {
-        super::Runspec::default().doc.clone()
+        super::Runspec::default().doc
     }
     pub fn lib() -> bool {
-        super::Runspec::default().lib.clone()
+        super::Runspec::default().lib
     }
     pub fn integration() -> Vec<String> {
         super::Runspec::default().integration.clone()
     }
+    pub fn lenient() -> bool {
+        super::Runspec::default().lenient
+    }
 }
 
-mod filters {
-    use is_executable::IsExecutable;
-    use std::cmp::Ordering;
-    use std::ffi::OsStr;
-    use std::fs;
-    use std::path;
+/// Format can be given either as a single value or as a list of them.
+pub(crate) mod formats {
+    use super::OutputFormat;
+    use serde::{Deserialize, Deserializer};
 
-    pub fn file_with_content(f: &fs::DirEntry) -> bool {
-        if let Ok(meta) = f.metadata() {
-            meta.is_file() && meta.len() > 1
-        } else {
-            false
-        }
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(OutputFormat),
+        Many(Vec<OutputFormat>),
     }
 
-    pub fn to_path(f: fs::DirEntry) -> path::PathBuf {
-        f.path()
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<OutputFormat>, D::Error> {
+        Ok(match OneOrMany::deserialize(deserializer)? {
+            OneOrMany::One(format) => vec![format],
+            OneOrMany::Many(formats) => formats,
+        })
     }
 
-    pub fn is_executable(f: &path::PathBuf) -> bool {
-        f.is_executable()
+    pub fn deserialize_option<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Vec<OutputFormat>>, D::Error> {
+        deserialize(deserializer).map(Some)
     }
+}
+
+/// Binaries, examples and benches are selected either with a bool or a list of names.
+pub(crate) mod targets {
+    use serde::{Deserialize, Deserializer};
 
-    pub fn extension_is(f: &path::PathBuf, suffix: &str) -> bool {
-        let rust_ext = OsStr::new(suffix);
-        f.extension() == Some(&rust_ext)
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum BoolOrList {
+        Bool(bool),
+        List(Vec<String>),
     }
 
-    pub fn filename_starts_with(f: &path::PathBuf, prefix: &str) -> bool {
-        if let Some(filename) = f.file_name() {
-            let filename = filename.to_str().unwrap_or("");
-            filename.starts_with(prefix)
-        } else {
-            false
-        }
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+        Ok(match BoolOrList::deserialize(deserializer)? {
+            BoolOrList::Bool(true) => vec![String::from("*")],
+            BoolOrList::Bool(false) => Vec::new(),
+            BoolOrList::List(names) => names,
+        })
+    }
+
+    pub fn deserialize_option<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Vec<String>>, D::Error> {
+        deserialize(deserializer).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{shared_features, ExitCodePolicy, FeatureMatrix, OutputFormat, Runspec, RunspecResult};
+    use crate::artifacts::parse_artifacts;
+    use crate::metadata::Metadata;
+    use std::path::Path;
+
+    #[test]
+    fn status_exit_codes() {
+        let policy = ExitCodePolicy::Status;
+        assert_eq!(0, policy.exit_code(&[RunspecResult::Ok, RunspecResult::Ok]));
+        assert_eq!(1, policy.exit_code(&[RunspecResult::Ok, RunspecResult::Errors(256)]));
+        assert_eq!(2, policy.exit_code(&[RunspecResult::Errors(3), RunspecResult::BuildFailed]));
+    }
+
+    #[test]
+    fn count_exit_codes() {
+        let policy = ExitCodePolicy::Count;
+        assert_eq!(0, policy.exit_code(&[RunspecResult::Ok]));
+        assert_eq!(7, policy.exit_code(&[RunspecResult::Errors(3), RunspecResult::Errors(4)]));
+        assert_eq!(100, policy.exit_code(&[RunspecResult::Errors(256)]));
+        assert_eq!(100, policy.exit_code(&[RunspecResult::Errors(101)]));
+        assert_eq!(102, policy.exit_code(&[RunspecResult::BuildFailed]));
+        assert_eq!(102, policy.exit_code(&[RunspecResult::Errors(3), RunspecResult::BuildFailed]));
+    }
+
+    #[test]
+    fn single_or_many_formats() {
+        let single: Runspec = toml::from_str(r#"format = "JUnit""#).unwrap();
+        let many: Runspec = toml::from_str(r#"format = ["JUnit"]"#).unwrap();
+        let missing: Runspec = toml::from_str("").unwrap();
+
+        assert_eq!(vec![OutputFormat::JUnit], single.format);
+        assert_eq!(vec![OutputFormat::JUnit], many.format);
+        assert_eq!(vec![OutputFormat::JUnit], missing.format);
+    }
+
+    #[test]
+    fn streamed_formats_are_listeners() {
+        let spec: Runspec = toml::from_str(r#"format = ["JUnit", "TeamCity"]"#).unwrap();
+
+        assert!(OutputFormat::TeamCity.reporter().is_none());
+        assert!(OutputFormat::JUnit.listener(Default::default()).is_none());
+        assert_eq!(1, spec.listeners(&[]).len());
+    }
+
+    #[test]
+    fn run_wide_formats_are_run_reporters() {
+        assert!(OutputFormat::Html.reporter().is_none());
+        assert!(OutputFormat::Markdown.reporter().is_none());
+        assert!(OutputFormat::Html.run_reporter().is_some());
+        assert!(OutputFormat::JUnit.run_reporter().is_none());
+        assert!(OutputFormat::GitHub.run_reporter().is_none());
+    }
+
+    #[test]
+    fn feature_selection_args() {
+        let spec: Runspec = toml::from_str(
+            r#"
+features = ["serde", "tokio"]
+default_features = false
+"#,
+        )
+        .unwrap();
+        assert_eq!(vec!["test", "--features", "serde tokio", "--no-default-features"], spec.get_shared_args());
+
+        let spec: Runspec = toml::from_str("all_features = true").unwrap();
+        assert_eq!(vec!["test", "--all-features"], spec.get_shared_args());
+    }
+
+    #[test]
+    fn powerset_combinations() {
+        let declared: Vec<String> = ["a", "b", "c", "nightly"].iter().map(|f| f.to_string()).collect();
+        let matrix: Runspec = toml::from_str(r#"feature_matrix = { exclude = ["nightly"] }"#).unwrap();
+        let combinations = matrix.feature_matrix.unwrap().combinations(&declared);
+        let expected: Vec<Vec<&str>> = vec![vec![], vec!["a"], vec!["b"], vec!["c"], vec!["a", "b"], vec!["a", "c"], vec!["b", "c"]];
+        assert_eq!(expected, combinations);
+
+        let shallow = FeatureMatrix::Powerset { depth: 1, exclude: Vec::new() };
+        assert_eq!(5, shallow.combinations(&declared).len());
+    }
+
+    #[test]
+    fn expand_feature_matrix() {
+        let spec: Runspec = toml::from_str(
+            r#"
+features = ["serde"]
+feature_matrix = [[], ["serde-json"], ["serde-json", "tokio"]]
+"#,
+        )
+        .unwrap();
+        // Explicit combinations never look at the manifest.
+        let specs = spec.expand_feature_matrix(Path::new("does/not/exist")).unwrap();
+        let names: Vec<&str> = specs.iter().map(|spec| spec.name.as_str()).collect();
+        assert_eq!(vec!["default", "default[+serde-json]", "default[+serde-json+tokio]"], names);
+        assert_eq!(vec!["serde", "serde-json", "tokio"], specs[2].features);
+        assert!(specs.iter().all(|spec| spec.feature_matrix.is_none()));
+
+        let plain = Runspec::default().expand_feature_matrix(Path::new("does/not/exist")).unwrap();
+        assert_eq!(1, plain.len());
     }
-    pub fn sort_by_modify_date(a: &path::PathBuf, b: &path::PathBuf) -> Ordering {
-        let a_meta = a.metadata().map(|a| a.modified().unwrap()).unwrap();
-        let b_meta = b.metadata().map(|b| b.modified().unwrap()).unwrap();
-        a_meta.cmp(&b_meta)
+
+    #[test]
+    fn features_shared_by_packages() {
+        let metadata = Metadata::parse(r#"{"packages":[
+{"name":"core","id":"core 0.1.0","targets":[],"features":{"default":["serde"],"serde":[],"tokio":[],"nightly":[]}},
+{"name":"cli","id":"cli 0.1.0","targets":[],"features":{"serde":["core/serde"],"nightly":[]}}
+]}"#)
+        .unwrap();
+
+        assert_eq!(vec!["nightly", "serde", "tokio"], shared_features(&metadata.packages[..1]));
+        assert_eq!(vec!["nightly", "serde"], shared_features(&metadata.packages));
+        assert!(shared_features(&[]).is_empty());
+    }
+
+    #[test]
+    fn workspace_jobs() {
+        let metadata = Metadata::parse(r#"{"packages":[
+{"name":"core","id":"core 0.1.0","targets":[{"kind":["lib"],"name":"core"},{"kind":["test"],"name":"api"}]},
+{"name":"cli","id":"cli 0.1.0","targets":[{"kind":["bin"],"name":"cli"},{"kind":["test"],"name":"smoke"}]}
+]}"#)
+        .unwrap();
+        let artifacts = parse_artifacts(
+            r#"{"reason":"compiler-artifact","package_id":"core 0.1.0","target":{"kind":["test"],"name":"api"},"profile":{"test":true},"executable":"/target/debug/deps/api-1111"}
+{"reason":"compiler-artifact","package_id":"cli 0.1.0","target":{"kind":["test"],"name":"smoke"},"profile":{"test":true},"executable":"/target/debug/deps/smoke-2222"}"#,
+        );
+        let spec = Runspec {
+            workspace: true,
+            ..Runspec::default()
+        };
+
+        let jobs = spec.jobs(&metadata.packages, &artifacts).unwrap();
+        let suites: Vec<&str> = jobs.iter().map(|job| job.suite.as_str()).collect();
+        // Binary crate has neither unit tests of a library nor doc tests.
+        assert_eq!(
+            vec!["[default] core::Lib-tests", "[default] core::Doc-tests", "[default] core::api", "[default] cli::smoke"],
+            suites
+        );
+        let args: Vec<_> = jobs[0].command.get_args().collect();
+        assert_eq!(&["test", "-p", "core", "--lib"], &args[..4]);
+        assert_eq!("/target/debug/deps/smoke-2222", jobs[3].command.get_program());
+
+        let listed = Runspec {
+            integration: vec![String::from("smoke")],
+            ..Runspec::default()
+        };
+        assert_eq!(3, listed.jobs(&metadata.packages, &artifacts).unwrap().len());
+        let missing = Runspec {
+            integration: vec![String::from("nope")],
+            ..Runspec::default()
+        };
+        assert!(missing.jobs(&metadata.packages, &artifacts).is_err());
+    }
+
+    #[test]
+    fn binaries_examples_and_benches() {
+        let spec: Runspec = toml::from_str(
+            r#"
+lib = false
+doc = false
+integration = []
+bins = true
+examples = ["demo"]
+benches = false
+"#,
+        )
+        .unwrap();
+        assert_eq!(vec!["--bins", "--example", "demo"], spec.extra_target_args());
+
+        let artifacts = parse_artifacts(
+            r#"{"reason":"compiler-artifact","package_id":"app 0.1.0","target":{"kind":["bin"],"name":"tool"},"profile":{"test":true},"executable":"/target/debug/deps/tool-1111"}
+{"reason":"compiler-artifact","package_id":"app 0.1.0","target":{"kind":["bin"],"name":"tool"},"profile":{"test":false},"executable":"/target/debug/tool"}
+{"reason":"compiler-artifact","package_id":"app 0.1.0","target":{"kind":["example"],"name":"demo"},"profile":{"test":true},"executable":"/target/debug/examples/demo-2222"}
+{"reason":"compiler-artifact","package_id":"app 0.1.0","target":{"kind":["bench"],"name":"speed"},"profile":{"test":true},"executable":"/target/debug/deps/speed-3333"}"#,
+        );
+        let jobs = spec.jobs(&[], &artifacts).unwrap();
+        let suites: Vec<&str> = jobs.iter().map(|job| job.suite.as_str()).collect();
+        assert_eq!(vec!["[default] bin::tool", "[default] example::demo"], suites);
+        assert_eq!("/target/debug/deps/tool-1111", jobs[0].command.get_program());
+
+        assert!(Runspec::default().extra_target_args().is_empty());
     }
 }
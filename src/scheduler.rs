@@ -0,0 +1,103 @@
+//! Concurrent execution of test processes.
+
+use crate::errors::SuityError;
+use crate::junit::{TestCase, TestSuite};
+use crate::listener::Listener;
+use crate::results::Event;
+use crate::runspec::Job;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::thread;
+
+/// Run jobs on up to `concurrency` threads. Results are returned in the same order as jobs were
+/// given, regardless of the order they finished in.
+pub fn run_all<L: Listener + Send>(
+    jobs: Vec<Job>,
+    concurrency: usize,
+    listener: &mut L,
+) -> Vec<Result<Option<TestSuite>, SuityError>> {
+    let total = jobs.len();
+    let concurrency = concurrency.clamp(1, total.max(1));
+    let queue: Mutex<VecDeque<(usize, Job)>> = Mutex::new(jobs.into_iter().enumerate().collect());
+    let results = Mutex::new((0..total).map(|_| None).collect::<Vec<_>>());
+    let listener = Mutex::new(listener);
+
+    thread::scope(|scope| {
+        for _ in 0..concurrency {
+            scope.spawn(|| loop {
+                let next = queue.lock().unwrap().pop_front();
+                let (idx, job) = match next {
+                    Some(next) => next,
+                    None => break,
+                };
+                let result = job.run(&mut Shared(&listener));
+                results.lock().unwrap()[idx] = Some(result);
+            });
+        }
+    });
+
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(|result| result.expect("every job has been run"))
+        .collect()
+}
+
+/// Number of jobs to run at once. Zero means one per CPU.
+pub fn effective_concurrency(jobs: usize) -> usize {
+    if jobs == 0 {
+        thread::available_parallelism().map(|n| n.get()).unwrap_or(1)
+    } else {
+        jobs
+    }
+}
+
+/// Listener shared between worker threads.
+struct Shared<'a, 'b, L>(&'a Mutex<&'b mut L>);
+
+impl<'a, 'b, L: Listener> Listener for Shared<'a, 'b, L> {
+    fn suite_started(&mut self, suite: &str) {
+        self.0.lock().unwrap().suite_started(suite)
+    }
+    fn event(&mut self, suite: &str, event: &Event) {
+        self.0.lock().unwrap().event(suite, event)
+    }
+    fn test_finished(&mut self, suite: &str, test_case: &TestCase) {
+        self.0.lock().unwrap().test_finished(suite, test_case)
+    }
+    fn suite_finished(&mut self, suite: &TestSuite) {
+        self.0.lock().unwrap().suite_finished(suite)
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::run_all;
+    use crate::runner::Limits;
+    use crate::runspec::Job;
+    use std::process::Command;
+
+    fn job(name: &str, delay: &str) -> Job {
+        let script = format!(
+            r#"sleep {}; echo '{{ "type": "suite", "event": "started", "test_count": 1 }}'; echo '{{ "type": "test", "name": "{}", "event": "ok" }}'"#,
+            delay, name
+        );
+        let mut command = Command::new("sh");
+        command.arg("-c").arg(script);
+        Job::new(String::from(name), command, Limits::default(), false)
+    }
+
+    #[test]
+    fn results_keep_job_order() {
+        let jobs = vec![job("slow", "0.3"), job("medium", "0.1"), job("fast", "0")];
+
+        let results = run_all(jobs, 3, &mut ());
+
+        let names: Vec<String> = results
+            .into_iter()
+            .map(|result| result.unwrap().unwrap().name)
+            .collect();
+        assert_eq!(vec!["slow", "medium", "fast"], names);
+    }
+}
@@ -0,0 +1,129 @@
+//! Machine-readable summary of a whole run.
+
+use crate::junit::TestSuite;
+use crate::runspec::{ExitCodePolicy, RunspecResult};
+use std::io;
+
+/// Status of a single workflow.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum WorkflowStatus {
+    Passed,
+    /// Some tests failed or erred out.
+    Failed,
+    /// Tests didn't compile.
+    BuildFailed,
+}
+
+/// Outcome of a single workflow.
+#[derive(Debug, Serialize, PartialEq)]
+pub struct WorkflowSummary {
+    pub name: String,
+    pub status: WorkflowStatus,
+    pub tests: u64,
+    pub failures: u64,
+    pub errors: u64,
+    pub skipped: u64,
+}
+
+impl WorkflowSummary {
+    pub fn new(name: String, suites: &[TestSuite]) -> WorkflowSummary {
+        let failures = suites.iter().map(|s| s.failures).sum();
+        let errors = suites.iter().map(|s| s.errors).sum();
+        WorkflowSummary {
+            name,
+            status: if failures + errors > 0 {
+                WorkflowStatus::Failed
+            } else {
+                WorkflowStatus::Passed
+            },
+            tests: suites.iter().map(|s| s.tests).sum(),
+            failures,
+            errors,
+            skipped: suites.iter().map(|s| s.skipped).sum(),
+        }
+    }
+
+    pub fn build_failed(name: String) -> WorkflowSummary {
+        WorkflowSummary {
+            name,
+            status: WorkflowStatus::BuildFailed,
+            tests: 0,
+            failures: 0,
+            errors: 0,
+            skipped: 0,
+        }
+    }
+
+    pub fn result(&self) -> RunspecResult {
+        if self.status == WorkflowStatus::BuildFailed {
+            RunspecResult::BuildFailed
+        } else if self.failures + self.errors > 0 {
+            RunspecResult::Errors(self.failures + self.errors)
+        } else {
+            RunspecResult::Ok
+        }
+    }
+}
+
+/// Totals across all workflows together with exit code suity is about to exit with.
+#[derive(Debug, Serialize, PartialEq)]
+pub struct Summary {
+    pub exit_code: i32,
+    pub tests: u64,
+    /// Tests that failed or erred out.
+    pub failed: u64,
+    pub skipped: u64,
+    pub workflows: Vec<WorkflowSummary>,
+}
+
+impl Summary {
+    pub fn new(workflows: Vec<WorkflowSummary>, policy: ExitCodePolicy) -> Summary {
+        let results: Vec<RunspecResult> = workflows.iter().map(WorkflowSummary::result).collect();
+        Summary {
+            exit_code: policy.exit_code(&results),
+            tests: workflows.iter().map(|w| w.tests).sum(),
+            failed: workflows.iter().map(|w| w.failures + w.errors).sum(),
+            skipped: workflows.iter().map(|w| w.skipped).sum(),
+            workflows,
+        }
+    }
+
+    pub fn write_as_json<W: io::Write>(&self, output: W) -> io::Result<()> {
+        serde_json::to_writer_pretty(output, self).map_err(io::Error::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Summary, WorkflowStatus, WorkflowSummary};
+    use crate::junit::TestSuite;
+    use crate::runspec::ExitCodePolicy;
+
+    #[test]
+    fn failed_and_broken_workflows() {
+        let mut suite = TestSuite::new(vec![], String::from("[default] Lib-tests")).unwrap();
+        suite.tests = 4;
+        suite.failures = 1;
+        suite.errors = 1;
+        suite.skipped = 1;
+        let workflows = vec![
+            WorkflowSummary::new(String::from("default"), &[suite]),
+            WorkflowSummary::build_failed(String::from("serde")),
+        ];
+
+        let summary = Summary::new(workflows, ExitCodePolicy::Status);
+
+        assert_eq!(2, summary.exit_code);
+        assert_eq!(4, summary.tests);
+        assert_eq!(2, summary.failed);
+        assert_eq!(1, summary.skipped);
+        assert_eq!(WorkflowStatus::Failed, summary.workflows[0].status);
+
+        let mut json = Vec::new();
+        summary.write_as_json(&mut json).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        assert_eq!(2, json["exit_code"]);
+        assert_eq!("build-failed", json["workflows"][1]["status"]);
+    }
+}
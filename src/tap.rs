@@ -0,0 +1,138 @@
+//! Support for export in TAP (Test Anything Protocol) version 14 format.
+//!
+//! Every suite is a subtest with its own plan, failures carry a YAML diagnostic block.
+
+use crate::junit::{TestCase, TestSuite};
+use crate::report::FileReporter;
+use crate::runspec::Runspec;
+use std::io::{self, Write};
+
+/// Writes TAP report.
+pub struct TapReporter;
+
+impl FileReporter for TapReporter {
+    fn extension(&self) -> &'static str {
+        "tap"
+    }
+
+    fn write(&self, _runspec: &Runspec, suites: &[TestSuite], output: &mut dyn Write) -> io::Result<()> {
+        write_as_tap(suites, output)
+    }
+}
+
+pub fn write_as_tap<W: Write>(suites: &[TestSuite], mut writer: W) -> io::Result<()> {
+    writeln!(writer, "TAP version 14")?;
+    for (idx, suite) in suites.iter().enumerate() {
+        writeln!(writer, "# Subtest: {}", suite.name)?;
+        writeln!(writer, "    1..{}", suite.test_cases.len())?;
+        for (case_idx, case) in suite.test_cases.iter().enumerate() {
+            write_test_case(&mut writer, case_idx + 1, case)?;
+        }
+        let ok = if suite.failures == 0 && suite.errors == 0 { "ok" } else { "not ok" };
+        writeln!(writer, "{} {} - {}", ok, idx + 1, escape(&suite.name))?;
+    }
+    writeln!(writer, "1..{}", suites.len())?;
+    Ok(())
+}
+
+fn write_test_case<W: Write>(writer: &mut W, number: usize, case: &TestCase) -> io::Result<()> {
+    let name = escape(&case.name);
+    if let Some(ref skipped) = case.skipped {
+        return match skipped.message {
+            Some(ref message) => writeln!(writer, "    ok {} - {} # SKIP {}", number, name, message),
+            None => writeln!(writer, "    ok {} - {} # SKIP", number, name),
+        };
+    }
+    if case.failure.is_none() && case.error.is_none() {
+        return writeln!(writer, "    ok {} - {}", number, name);
+    }
+
+    writeln!(writer, "    not ok {} - {}", number, name)?;
+    writeln!(writer, "      ---")?;
+    if let Some(ref failure) = case.failure {
+        write_yaml_text(writer, "message", &failure.message)?;
+        writeln!(writer, "      severity: fail")?;
+    }
+    if let Some(ref error) = case.error {
+        write_yaml_text(writer, "message", &error.message)?;
+        writeln!(writer, "      severity: error")?;
+        if let Some(ref output) = error.output {
+            write_yaml_text(writer, "output", output)?;
+        }
+    }
+    if let Some(time) = case.time {
+        writeln!(writer, "      duration_ms: {:.3}", time.as_secs_f64() * 1000.0)?;
+    }
+    writeln!(writer, "      ...")
+}
+
+/// Write text as YAML literal block, so nothing in it needs escaping.
+fn write_yaml_text<W: Write>(writer: &mut W, key: &str, text: &str) -> io::Result<()> {
+    if text.trim().is_empty() {
+        return writeln!(writer, "      {}: \"\"", key);
+    }
+    writeln!(writer, "      {}: |-", key)?;
+    for line in text.trim_end().lines() {
+        writeln!(writer, "        {}", line)?;
+    }
+    Ok(())
+}
+
+/// `#` starts a directive and `\` escapes it, both have to be escaped in descriptions.
+fn escape(description: &str) -> String {
+    description.replace('\\', "\\\\").replace('#', "\\#")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::write_as_tap;
+    use crate::junit::{crashed_suite, TestSuite};
+    use crate::results::parse_test_results;
+
+    #[test]
+    fn suite_with_every_outcome() {
+        let stdout = r#"{ "type": "suite", "event": "started", "test_count": 3 }
+{ "type": "test", "event": "started", "name": "works" }
+{ "type": "test", "name": "works", "event": "ok" }
+{ "type": "test", "event": "started", "name": "issue#12" }
+{ "type": "test", "name": "issue#12", "event": "failed", "stdout": "assertion failed\nleft: 1\n", "exec_time": 0.25 }
+{ "type": "test", "name": "needs_zfs", "event": "ignored", "message": "requires root" }
+{ "type": "suite", "event": "failed", "passed": 1, "failed": 1, "allowed_fail": 0, "ignored": 1, "measured": 0, "filtered_out": 0 }"#;
+        let events = parse_test_results(stdout).unwrap();
+        let suite = TestSuite::new(events, String::from("[default] Lib-tests")).unwrap();
+
+        let mut output = Vec::new();
+        write_as_tap(&[suite], &mut output).unwrap();
+
+        let expected = r#"TAP version 14
+# Subtest: [default] Lib-tests
+    1..3
+    ok 1 - works
+    not ok 2 - issue\#12
+      ---
+      message: |-
+        assertion failed
+        left: 1
+      severity: fail
+      duration_ms: 250.000
+      ...
+    ok 3 - needs_zfs # SKIP requires root
+not ok 1 - [default] Lib-tests
+1..1
+"#;
+        assert_eq!(expected, String::from_utf8(output).unwrap());
+    }
+
+    #[test]
+    fn crashed_case_is_an_error() {
+        let suite = crashed_suite();
+
+        let mut output = Vec::new();
+        write_as_tap(&[suite], &mut output).unwrap();
+        let tap = String::from_utf8(output).unwrap();
+
+        assert!(tap.contains("    not ok 4 - segfaults\n      ---\n"));
+        assert!(tap.contains("      severity: error\n"));
+        assert!(tap.contains("not ok 1 - [default] Lib-tests\n"));
+    }
+}
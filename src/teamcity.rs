@@ -0,0 +1,161 @@
+//! Support for TeamCity service messages, streamed to stdout while tests are running.
+
+use crate::junit::{TestCase, TestSuite};
+use crate::listener::Listener;
+use crate::results::{Event, EventKind};
+use std::collections::HashSet;
+use std::io::Write;
+
+/// Listener that writes service messages for selected suites. Suites may run in parallel, so
+/// every message carries `flowId` set to name of its suite.
+pub struct TeamCity<W> {
+    writer: W,
+    suites: HashSet<String>,
+}
+
+impl<W: Write> TeamCity<W> {
+    /// Report progress of `suites` to `writer`, other suites are ignored.
+    pub fn new(writer: W, suites: HashSet<String>) -> TeamCity<W> {
+        TeamCity { writer, suites }
+    }
+
+    fn message(&mut self, name: &str, suite: &str, attributes: &[(&str, &str)]) {
+        let mut message = format!("##teamcity[{} flowId='{}'", name, escape(suite));
+        for (key, value) in attributes {
+            message.push_str(&format!(" {}='{}'", key, escape(value)));
+        }
+        message.push(']');
+        // Progress reporting must not fail the run.
+        let _ = writeln!(self.writer, "{}", message);
+        let _ = self.writer.flush();
+    }
+
+    fn error(&mut self, suite: &str, case: &TestCase) {
+        let error = case.error.as_ref().expect("only called for errors");
+        let details = error.output.as_deref().unwrap_or("");
+        self.message(
+            "testFailed",
+            suite,
+            &[("name", &case.name), ("message", &error.message), ("details", details)],
+        );
+        self.message("testFinished", suite, &[("name", &case.name)]);
+    }
+}
+
+impl<W: Write> Listener for TeamCity<W> {
+    fn suite_started(&mut self, suite: &str) {
+        if self.suites.contains(suite) {
+            self.message("testSuiteStarted", suite, &[("name", suite)]);
+        }
+    }
+
+    fn event(&mut self, suite: &str, event: &Event) {
+        if !self.suites.contains(suite) {
+            return;
+        }
+        let test = match event {
+            Event::Test(test) => test,
+            Event::Suite(_) => return,
+        };
+        let name = test.name.as_str();
+        let duration = test
+            .exec_time
+            .map(|secs| ((secs * 1000.0).round() as u64).to_string());
+        let mut finished = vec![("name", name)];
+        if let Some(ref duration) = duration {
+            finished.push(("duration", duration));
+        }
+        match test.event {
+            EventKind::Started => self.message("testStarted", suite, &[("name", name)]),
+            EventKind::Ok => self.message("testFinished", suite, &finished),
+            EventKind::Failed => {
+                let details = test.stdout.as_deref().unwrap_or("");
+                let message = test.message.as_deref().unwrap_or("failed");
+                self.message(
+                    "testFailed",
+                    suite,
+                    &[("name", name), ("message", message), ("details", details)],
+                );
+                self.message("testFinished", suite, &finished);
+            }
+            EventKind::Ignored => {
+                let message = test.message.as_deref().unwrap_or("");
+                self.message("testIgnored", suite, &[("name", name), ("message", message)]);
+            }
+            // TeamCity tracks test duration on its own.
+            EventKind::Timeout => {}
+        }
+    }
+
+    fn suite_finished(&mut self, suite: &TestSuite) {
+        if !self.suites.contains(&suite.name) {
+            return;
+        }
+        // Errors are only known once process exits, they never come as events.
+        for case in suite.test_cases.iter().filter(|case| case.error.is_some()) {
+            self.error(&suite.name, case);
+        }
+        self.message("testSuiteFinished", &suite.name, &[("name", &suite.name)]);
+    }
+}
+
+/// Escape value of an attribute according to TeamCity rules.
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '|' => escaped.push_str("||"),
+            '\'' => escaped.push_str("|'"),
+            '\n' => escaped.push_str("|n"),
+            '\r' => escaped.push_str("|r"),
+            '[' => escaped.push_str("|["),
+            ']' => escaped.push_str("|]"),
+            '\u{0085}' => escaped.push_str("|x"),
+            '\u{2028}' => escaped.push_str("|l"),
+            '\u{2029}' => escaped.push_str("|p"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TeamCity;
+    use crate::junit::{crashed_suite, CRASHED_SUITE_OUTPUT};
+    use crate::listener::Listener;
+    use crate::results::parse_test_results;
+
+    #[test]
+    fn streamed_messages() {
+        let events = parse_test_results(CRASHED_SUITE_OUTPUT).unwrap();
+        let suite = crashed_suite();
+        let suite_name = suite.name.clone();
+
+        let mut output = Vec::new();
+        {
+            let mut teamcity = TeamCity::new(&mut output, vec![suite_name.clone()].into_iter().collect());
+            teamcity.suite_started(&suite_name);
+            for event in &events {
+                teamcity.event(&suite_name, event);
+            }
+            teamcity.suite_finished(&suite);
+            // Suites of other workflows are left alone.
+            teamcity.suite_started("[other] Lib-tests");
+        }
+
+        let expected = "##teamcity[testSuiteStarted flowId='|[default|] Lib-tests' name='|[default|] Lib-tests']
+##teamcity[testStarted flowId='|[default|] Lib-tests' name='works']
+##teamcity[testFinished flowId='|[default|] Lib-tests' name='works' duration='250']
+##teamcity[testStarted flowId='|[default|] Lib-tests' name='broken']
+##teamcity[testFailed flowId='|[default|] Lib-tests' name='broken' message='failed' details='thread |'broken|' panicked at |'boom|', src/lib.rs:7:5|n']
+##teamcity[testFinished flowId='|[default|] Lib-tests' name='broken' duration='500']
+##teamcity[testIgnored flowId='|[default|] Lib-tests' name='needs_zfs' message='requires root']
+##teamcity[testStarted flowId='|[default|] Lib-tests' name='segfaults']
+##teamcity[testFailed flowId='|[default|] Lib-tests' name='segfaults' message='terminated by signal 11 (SIGSEGV)' details='oops']
+##teamcity[testFinished flowId='|[default|] Lib-tests' name='segfaults']
+##teamcity[testSuiteFinished flowId='|[default|] Lib-tests' name='|[default|] Lib-tests']
+";
+        assert_eq!(expected, String::from_utf8(output).unwrap());
+    }
+}
@@ -0,0 +1,209 @@
+//! Support for export in Visual Studio TRX format.
+
+use crate::ids::guid;
+use crate::junit::{TestCase, TestSuite};
+use crate::panic::summarize_failure;
+use crate::report::FileReporter;
+use crate::runspec::Runspec;
+use std::io::{self, Write};
+use std::time::Duration;
+use xml_writer::XmlWriter;
+
+const NAMESPACE: &str = "http://microsoft.com/schemas/VisualStudio/TeamTest/2010";
+/// Well-known type of unit tests.
+const UNIT_TEST_TYPE: &str = "13cdc9d9-ddb5-4fa4-a97d-d965ccfc6d4b";
+/// Well-known list every result belongs to.
+const RESULTS_NOT_IN_A_LIST: &str = "8c84fa94-04c1-424b-9868-57a2d4851a1d";
+const ALL_LOADED_RESULTS: &str = "19431567-8539-422a-85d7-44ee4e166bda";
+
+/// Writes TRX report.
+pub struct TrxReporter;
+
+impl FileReporter for TrxReporter {
+    fn extension(&self) -> &'static str {
+        "trx"
+    }
+
+    fn write(&self, runspec: &Runspec, suites: &[TestSuite], output: &mut dyn Write) -> io::Result<()> {
+        write_as_trx(&runspec.name, suites, output)
+    }
+}
+
+/// Outcome of a test in TRX terms.
+fn outcome(case: &TestCase) -> &'static str {
+    if case.failure.is_some() || case.error.is_some() {
+        "Failed"
+    } else if case.skipped.is_some() {
+        "NotExecuted"
+    } else {
+        "Passed"
+    }
+}
+
+/// `hh:mm:ss.fffffff`
+fn format_duration(duration: &Duration) -> String {
+    let secs = duration.as_secs();
+    format!(
+        "{:02}:{:02}:{:02}.{:07}",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60,
+        duration.subsec_nanos() / 100
+    )
+}
+
+pub fn write_as_trx<W: Write>(name: &str, suites: &[TestSuite], writer: W) -> io::Result<()> {
+    let cases = || {
+        suites
+            .iter()
+            .flat_map(|suite| suite.test_cases.iter().map(move |case| (suite, case)))
+    };
+    let test_id = |suite: &TestSuite, case: &TestCase| guid(&[name, &suite.name, &case.name]);
+    let execution_id = |suite: &TestSuite, case: &TestCase| guid(&[name, &suite.name, &case.name, "execution"]);
+
+    let mut xml = XmlWriter::new(writer);
+    xml.dtd("utf-8")?;
+    xml.begin_elem("TestRun")?;
+    xml.attr("id", &guid(&[name]))?;
+    xml.attr_esc("name", name)?;
+    xml.attr("xmlns", NAMESPACE)?;
+
+    xml.begin_elem("Results")?;
+    for (suite, case) in cases() {
+        xml.begin_elem("UnitTestResult")?;
+        xml.attr("executionId", &execution_id(suite, case))?;
+        xml.attr("testId", &test_id(suite, case))?;
+        xml.attr_esc("testName", &case.name)?;
+        xml.attr("computerName", "localhost")?;
+        if let Some(ref time) = case.time {
+            xml.attr("duration", &format_duration(time))?;
+        }
+        xml.attr("testType", UNIT_TEST_TYPE)?;
+        xml.attr("outcome", outcome(case))?;
+        xml.attr("testListId", RESULTS_NOT_IN_A_LIST)?;
+        write_output(&mut xml, case)?;
+        xml.end_elem()?;
+    }
+    xml.end_elem()?;
+
+    xml.begin_elem("TestDefinitions")?;
+    for (suite, case) in cases() {
+        xml.begin_elem("UnitTest")?;
+        xml.attr_esc("name", &case.name)?;
+        xml.attr_esc("storage", &suite.name)?;
+        xml.attr("id", &test_id(suite, case))?;
+        xml.begin_elem("Execution")?;
+        xml.attr("id", &execution_id(suite, case))?;
+        xml.end_elem()?;
+        xml.begin_elem("TestMethod")?;
+        xml.attr_esc("codeBase", &suite.name)?;
+        xml.attr_esc("className", &suite.name)?;
+        xml.attr_esc("name", &case.name)?;
+        xml.end_elem()?;
+        xml.end_elem()?;
+    }
+    xml.end_elem()?;
+
+    xml.begin_elem("TestEntries")?;
+    for (suite, case) in cases() {
+        xml.begin_elem("TestEntry")?;
+        xml.attr("testId", &test_id(suite, case))?;
+        xml.attr("executionId", &execution_id(suite, case))?;
+        xml.attr("testListId", RESULTS_NOT_IN_A_LIST)?;
+        xml.end_elem()?;
+    }
+    xml.end_elem()?;
+
+    xml.begin_elem("TestLists")?;
+    for (id, list) in &[(RESULTS_NOT_IN_A_LIST, "Results Not in a List"), (ALL_LOADED_RESULTS, "All Loaded Results")] {
+        xml.begin_elem("TestList")?;
+        xml.attr("name", list)?;
+        xml.attr("id", id)?;
+        xml.end_elem()?;
+    }
+    xml.end_elem()?;
+
+    let count = |outcome_name: &str| cases().filter(|(_, case)| outcome(case) == outcome_name).count();
+    let (passed, failed, not_executed) = (count("Passed"), count("Failed"), count("NotExecuted"));
+    xml.begin_elem("ResultSummary")?;
+    xml.attr("outcome", if failed == 0 { "Completed" } else { "Failed" })?;
+    xml.begin_elem("Counters")?;
+    xml.attr("total", &(passed + failed + not_executed).to_string())?;
+    xml.attr("executed", &(passed + failed).to_string())?;
+    xml.attr("passed", &passed.to_string())?;
+    xml.attr("failed", &failed.to_string())?;
+    xml.attr("notExecuted", &not_executed.to_string())?;
+    xml.end_elem()?;
+    let suite_output: Vec<&str> = suites.iter().filter_map(|suite| suite.system_out.as_deref()).collect();
+    if !suite_output.is_empty() {
+        xml.begin_elem("Output")?;
+        xml.elem_text("StdOut", &suite_output.join("\n"))?;
+        xml.end_elem()?;
+    }
+    xml.end_elem()?;
+
+    xml.end_elem()?;
+    xml.close()?;
+    xml.flush()
+}
+
+/// Captured stdout goes to `StdOut`, stderr of crashed process to `StdErr`, short description of
+/// why test failed to `ErrorInfo`.
+fn write_output<W: Write>(xml: &mut XmlWriter<W>, case: &TestCase) -> io::Result<()> {
+    let (stdout, stderr, message) = if let Some(ref failure) = case.failure {
+        (Some(failure.message.as_str()), None, Some(summarize_failure(&failure.message)))
+    } else if let Some(ref error) = case.error {
+        (case.system_out.as_deref(), error.output.as_deref(), Some(error.message.clone()))
+    } else if let Some(ref skipped) = case.skipped {
+        (case.system_out.as_deref(), None, skipped.message.clone())
+    } else {
+        (case.system_out.as_deref(), None, None)
+    };
+    if stdout.is_none() && stderr.is_none() && message.is_none() {
+        return Ok(());
+    }
+    xml.begin_elem("Output")?;
+    if let Some(stdout) = stdout {
+        xml.elem_text("StdOut", stdout)?;
+    }
+    if let Some(stderr) = stderr {
+        xml.elem_text("StdErr", stderr)?;
+    }
+    if let Some(ref message) = message {
+        xml.begin_elem("ErrorInfo")?;
+        xml.elem_text("Message", message)?;
+        xml.end_elem()?;
+    }
+    xml.end_elem()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_duration, write_as_trx};
+    use crate::junit::crashed_suite;
+    use std::time::Duration;
+
+    #[test]
+    fn durations() {
+        assert_eq!("00:00:00.2500000", format_duration(&Duration::from_millis(250)));
+        assert_eq!("01:01:05.0000001", format_duration(&Duration::new(3665, 100)));
+    }
+
+    #[test]
+    fn outcomes_and_output() {
+        let suite = crashed_suite();
+
+        let mut output = Vec::new();
+        write_as_trx("default", &[suite], &mut output).unwrap();
+        let trx = String::from_utf8(output).unwrap();
+
+        assert!(trx.contains(r#"testName="works" computerName="localhost" duration="00:00:00.2500000" testType="13cdc9d9-ddb5-4fa4-a97d-d965ccfc6d4b" outcome="Passed""#));
+        assert!(trx.contains(r#"testName="broken" computerName="localhost" duration="00:00:00.5000000" testType="13cdc9d9-ddb5-4fa4-a97d-d965ccfc6d4b" outcome="Failed""#));
+        assert!(trx.contains("<StdOut>thread &apos;broken&apos; panicked at &apos;boom&apos;, src/lib.rs:7:5\n</StdOut>"));
+        assert!(trx.contains("<Message>boom</Message></ErrorInfo>"));
+        assert!(trx.contains(r#"testName="needs_zfs" computerName="localhost" testType="13cdc9d9-ddb5-4fa4-a97d-d965ccfc6d4b" outcome="NotExecuted""#));
+        assert!(trx.contains("<StdErr>oops</StdErr>"));
+        assert!(trx.contains(r#"<ResultSummary outcome="Failed">"#));
+        assert!(trx.contains(r#"<Counters total="4" executed="3" passed="1" failed="2" notExecuted="1">"#));
+    }
+}
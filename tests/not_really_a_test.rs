@@ -1,4 +1,5 @@
 #[test]
+#[allow(clippy::assertions_on_constants)]
 fn test_add() {
     assert!(true);
 }